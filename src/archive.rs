@@ -0,0 +1,588 @@
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::github;
+use crate::http::{self, BodyReader, FetchError};
+use crate::i18n::t;
+use crate::partial_download::PartialDownload;
+use crate::{diagnostics, events, theme};
+
+/// Starts each entry in a zip file's central directory
+const CENTRAL_DIRECTORY_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x01, 0x02];
+/// Starts each file in a zip archive, and so the archive itself
+const ZIP_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x03, 0x04];
+const GZIP_SIGNATURE: [u8; 2] = [0x1f, 0x8b];
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+/// Unix permissions are only set by archivers that say they're from unix
+const UNIX: u8 = 3;
+
+/// The formats GitHub serves archives of a repo in
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Download the zip archive, but extract whatever format is actually
+    /// served, going by its Content-Type and first bytes
+    Auto,
+    Zip,
+    /// Smaller than the zip archive, and left alone by proxies that mangle
+    /// zip files
+    TarGz,
+}
+
+impl Format {
+    /// The file extension GitHub uses for archives in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Auto | Format::Zip => "zip",
+            Format::TarGz => "tar.gz",
+        }
+    }
+
+    /// The format the archive is actually in. `Auto` goes by the first
+    /// bytes, as servers don't always send the right Content-Type, and only
+    /// falls back to the Content-Type if they're not recognized
+    pub fn detect(self, content_type: Option<&str>, start: &[u8]) -> Format {
+        if self != Format::Auto {
+            return self;
+        }
+
+        let content_type = content_type.unwrap_or_default().to_ascii_lowercase();
+
+        let format = if start.starts_with(&ZIP_SIGNATURE) {
+            Format::Zip
+        } else if start.starts_with(&GZIP_SIGNATURE) || content_type.contains("gzip") {
+            Format::TarGz
+        } else {
+            Format::Zip
+        };
+
+        tracing::debug!("Detected the archive format as {format:?} ({content_type})");
+
+        format
+    }
+}
+
+/// Downloads the archive and makes sure it's intact before handing it over
+/// for extraction. Returns it with the format it turned out to be in.
+///
+/// Truncated downloads and archives that can't be read are retried, and
+/// truncated ones resume where they stopped. A `sha256` mismatch is not
+/// retried, as it most likely means the archive has changed since the digest
+/// was recorded
+pub fn download(url: &str, sha256: Option<&str>, format: Format) -> (Vec<u8>, Format) {
+    http::with_retries(&t!("download.action"), || {
+        let mut partial = PartialDownload::open(url);
+        let (response, body) = github::open(url, partial.range_headers())?;
+        partial.begin(response.status, &response.headers)?;
+
+        let content_type = http::content_type(&response.headers);
+        let archive = read_complete_body(&mut partial, body)?;
+        let format = format.detect(content_type.as_deref(), &archive);
+
+        // The download is complete, so there's nothing left to resume. If
+        // it's corrupted, the next attempt has to start over anyway
+        partial.discard();
+
+        verify(&archive, format)?;
+
+        if let Some(expected) = sha256 {
+            verify_sha256(&archive, expected)?;
+        }
+
+        Ok((archive, format))
+    })
+}
+
+/// Reads an archive that's already been downloaded, and checks it the same
+/// way `download` does. Exits if it can't be used
+pub fn read_file(path: &Path, sha256: Option<&str>, format: Format) -> (Vec<u8>, Format) {
+    let archive = fs::read(path).unwrap_or_else(|e| {
+        eprintln!(
+            "{}",
+            theme::error(t!(
+                "errors.cant_read",
+                file = path.to_string_lossy(),
+                error = e
+            ))
+        );
+        diagnostics::fail();
+    });
+    let format = format.detect(None, &archive);
+
+    let checked = verify(&archive, format).and_then(|()| match sha256 {
+        Some(expected) => verify_sha256(&archive, expected),
+        None => Ok(()),
+    });
+
+    if let Err(FetchError::Retryable(message) | FetchError::Permanent(message)) = checked {
+        eprintln!(
+            "{}",
+            theme::error(t!(
+                "download.cant_use",
+                file = path.to_string_lossy(),
+                error = message
+            ))
+        );
+        diagnostics::fail();
+    }
+
+    (archive, format)
+}
+
+/// Extracts the archive into `target_dir` as it's read, stripping away its
+/// top level directory, which GitHub names after the repo and branch. With
+/// `only`, everything outside of that dir is skipped, which is most of the
+/// repo.
+///
+/// Returns the commit the archive was made from. GitHub puts it in the zip
+/// file's comment, and in the tarball's global pax header
+pub fn extract(
+    format: Format,
+    reader: impl Read,
+    target_dir: &Path,
+    only: Option<&Path>,
+) -> io::Result<Option<String>> {
+    match format {
+        Format::Auto | Format::Zip => extract_zip(reader, target_dir, only),
+        Format::TarGz => extract_tar_gz(reader, target_dir, only),
+    }
+}
+
+fn extract_zip(
+    mut reader: impl Read,
+    target_dir: &Path,
+    only: Option<&Path>,
+) -> io::Result<Option<String>> {
+    let mut entries = 0;
+    let mut skipped = 0;
+
+    // Skipped entries still have to be read, to get to the next one
+    while let Some(mut file) = zip::read::read_zipfile_from_stream(&mut reader)? {
+        let Some(path) = file.enclosed_name().and_then(strip_toplevel) else {
+            continue;
+        };
+        if !is_included(&path, only) {
+            skipped += 1;
+            continue;
+        }
+        let path = target_dir.join(path);
+        entries += 1;
+
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        io::copy(&mut file, &mut fs::File::create(&path)?)?;
+    }
+
+    tracing::debug!("Extracted {entries} entries, skipped {skipped}");
+
+    // Reading the entries stops right after the central directory's first
+    // signature. The central directory is the rest of the archive
+    let mut central_directory = CENTRAL_DIRECTORY_SIGNATURE.to_vec();
+    reader.read_to_end(&mut central_directory)?;
+
+    #[cfg(unix)]
+    set_permissions(&central_directory, target_dir)?;
+
+    Ok(commit_from_tail(&central_directory))
+}
+
+fn extract_tar_gz(
+    reader: impl Read,
+    target_dir: &Path,
+    only: Option<&Path>,
+) -> io::Result<Option<String>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+    let mut commit = None;
+    let mut entries = 0;
+    let mut skipped = 0;
+
+    fs::create_dir_all(target_dir)?;
+    let canonical_target_dir = fs::canonicalize(target_dir)?;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        if entry.header().entry_type() == tar::EntryType::XGlobalHeader {
+            for extension in entry.pax_extensions()?.into_iter().flatten() {
+                let extension = extension?;
+
+                if extension.key() == Ok("comment") {
+                    commit = commit_from_comment(extension.value_bytes());
+                }
+            }
+            continue;
+        }
+
+        let Some(path) = strip_toplevel(&entry.path()?) else {
+            continue;
+        };
+        if !is_included(&path, only) {
+            skipped += 1;
+            continue;
+        }
+        let path = target_dir.join(path);
+
+        // Hard links are to other entries by their path in the archive,
+        // which `unpack` doesn't know about. GitHub's archives have none
+        if entry.header().entry_type() == tar::EntryType::Link {
+            tracing::debug!("Skipping hard link {}", path.to_string_lossy());
+            skipped += 1;
+            continue;
+        }
+
+        let parent = path.parent().unwrap_or(target_dir);
+        fs::create_dir_all(parent)?;
+
+        // Symlinks only ever point inside of `target_dir`, so this can't
+        // happen. Unless that check has a hole, so make sure
+        let canonical_parent = fs::canonicalize(parent)?;
+        let Ok(dir) = canonical_parent.strip_prefix(&canonical_target_dir) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                t!("download.outside_target", path = path.to_string_lossy()),
+            ));
+        };
+
+        if entry.header().entry_type() == tar::EntryType::Symlink {
+            let target = entry.link_name()?.unwrap_or_default();
+
+            if !link_stays_inside(dir, &target) {
+                tracing::debug!(
+                    "Skipping symlink {} to {}, as it points outside of the archive",
+                    path.to_string_lossy(),
+                    target.to_string_lossy()
+                );
+                skipped += 1;
+                continue;
+            }
+        }
+
+        entries += 1;
+        entry.unpack(&path)?;
+    }
+
+    tracing::debug!("Extracted {entries} entries, skipped {skipped}");
+
+    Ok(commit)
+}
+
+/// Sets the unix permissions of the files extracted from a zip archive.
+/// They're only in the central directory, which is read last when streaming
+#[cfg(unix)]
+fn set_permissions(central_directory: &[u8], target_dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let u16_at = |at: usize| u16::from_le_bytes([central_directory[at], central_directory[at + 1]]);
+    let mut offset = 0;
+
+    // Each entry is a 46 byte header followed by the name, an extra field
+    // and a comment
+    while central_directory.len() >= offset + 46
+        && central_directory[offset..offset + 4] == CENTRAL_DIRECTORY_SIGNATURE
+    {
+        let made_by = central_directory[offset + 5];
+        let name_length = u16_at(offset + 28) as usize;
+        let extra_length = u16_at(offset + 30) as usize;
+        let comment_length = u16_at(offset + 32) as usize;
+        let external_attributes = u32::from_le_bytes(
+            central_directory[offset + 38..offset + 42]
+                .try_into()
+                .unwrap(),
+        );
+
+        let Some(name) = central_directory.get(offset + 46..offset + 46 + name_length) else {
+            break;
+        };
+        let name = String::from_utf8_lossy(name);
+        let mode = external_attributes >> 16;
+
+        let path = strip_toplevel(Path::new(name.as_ref())).map(|path| target_dir.join(path));
+
+        if let (UNIX, Some(path), true) = (made_by, path, mode != 0) {
+            if path.is_file() && path.starts_with(target_dir) && !name.contains("..") {
+                fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+            }
+        }
+
+        offset += 46 + name_length + extra_length + comment_length;
+    }
+
+    Ok(())
+}
+
+/// Whether `path`, relative to the top level directory, is `only` or in it
+fn is_included(path: &Path, only: Option<&Path>) -> bool {
+    only.is_none_or(|only| path.starts_with(only))
+}
+
+/// Removes the first component of `path`. `None` for the top level
+/// directory itself, and for paths that would end up outside of it
+fn strip_toplevel(path: &Path) -> Option<PathBuf> {
+    let mut components = path.components();
+
+    match components.next() {
+        Some(Component::Normal(_)) => {}
+        _ => return None,
+    }
+
+    let rest = components.as_path();
+    let is_enclosed = rest
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+
+    (is_enclosed && !rest.as_os_str().is_empty()).then(|| rest.to_path_buf())
+}
+
+/// Whether a symlink in `dir`, relative to the top level directory, to
+/// `target` points at something inside of it. Entries can be written
+/// through symlinks, so one to anywhere else would let the archive write
+/// there.
+///
+/// `..` is only allowed at the start of `target`, as anything after it
+/// might be a symlink itself, which `..` would go up from
+fn link_stays_inside(dir: &Path, target: &Path) -> bool {
+    let mut depth = dir.components().count();
+    let mut going_up = true;
+
+    for component in target.components() {
+        match component {
+            Component::ParentDir if going_up && depth > 0 => depth -= 1,
+            Component::Normal(_) => going_up = false,
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+
+    true
+}
+
+/// The commit a zip archive was made from. Only needs the end of the
+/// archive, where the comment is
+fn commit_from_tail(tail: &[u8]) -> Option<String> {
+    // The end of central directory record has a 4 byte signature, 16 bytes of
+    // offsets and sizes, and the comment's length, followed by the comment
+    const SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
+
+    let start = tail.windows(4).rposition(|window| window == SIGNATURE)?;
+    let record = &tail[start..];
+    let comment_length = u16::from_le_bytes([*record.get(20)?, *record.get(21)?]) as usize;
+
+    commit_from_comment(record.get(22..22 + comment_length)?)
+}
+
+fn commit_from_comment(comment: &[u8]) -> Option<String> {
+    let comment = String::from_utf8_lossy(comment).trim().to_owned();
+
+    let is_sha = comment.len() == 40 && comment.chars().all(|c| c.is_ascii_hexdigit());
+    is_sha.then_some(comment)
+}
+
+/// Reads what earlier attempts downloaded, followed by the rest of the body,
+/// which is kept for resuming as it comes in. The whole download is checked
+/// against its size, if the server told us
+fn read_complete_body(
+    partial: &mut PartialDownload,
+    mut body: BodyReader,
+) -> Result<Vec<u8>, FetchError> {
+    let retryable = |e: io::Error| FetchError::Retryable(e.to_string());
+    let mut archive = Vec::new();
+
+    if let Some(mut existing) = partial.existing().map_err(retryable)? {
+        existing.read_to_end(&mut archive).map_err(retryable)?;
+    }
+
+    let mut buf = vec![0; READ_BUFFER_SIZE];
+
+    loop {
+        let read = body.read(&mut buf).map_err(retryable)?;
+
+        if read == 0 {
+            break;
+        }
+
+        partial.append(&buf[..read]);
+        archive.extend_from_slice(&buf[..read]);
+        events::progress(archive.len() as u64, partial.total());
+    }
+
+    tracing::debug!("Downloaded {} bytes", archive.len());
+
+    match partial.total() {
+        Some(expected) if expected != archive.len() as u64 => {
+            // Only a download that came up short can be resumed
+            if archive.len() as u64 > expected {
+                partial.discard();
+            }
+
+            Err(FetchError::Retryable(t!(
+                "download.truncated",
+                expected = expected,
+                received = archive.len()
+            )))
+        }
+        _ => Ok(archive),
+    }
+}
+
+/// Catches most truncated or mangled downloads. Opening a zip archive reads
+/// its central directory, which is at the very end of the file. A tarball
+/// has to be read all the way through
+fn verify(archive: &[u8], format: Format) -> Result<(), FetchError> {
+    let result = match format {
+        Format::Auto | Format::Zip => zip::ZipArchive::new(Cursor::new(archive))
+            .map(|zip| tracing::debug!("Archive contains {} entries", zip.len()))
+            .map_err(|e| e.to_string()),
+        Format::TarGz => tar::Archive::new(GzDecoder::new(archive))
+            .entries()
+            .and_then(|mut entries| entries.try_for_each(|entry| entry.map(|_| ())))
+            .map_err(|e| e.to_string()),
+    };
+
+    result.map_err(|e| {
+        FetchError::Retryable(t!(
+            "download.invalid",
+            format = format.extension(),
+            error = e
+        ))
+    })
+}
+
+fn verify_sha256(archive: &[u8], expected: &str) -> Result<(), FetchError> {
+    compare_sha256(&format!("{:x}", Sha256::digest(archive)), expected)
+}
+
+/// Compares a hex encoded digest with the one we expect
+pub fn compare_sha256(actual: &str, expected: &str) -> Result<(), FetchError> {
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(FetchError::Permanent(t!(
+            "download.sha256_mismatch",
+            expected = expected,
+            actual = actual
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+
+    /// A tar.gz archive with the given symlinks, from path to target, and
+    /// files, from path to contents, in that order
+    fn tar_gz(symlinks: &[(&str, &str)], files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Default::default()));
+
+        for (path, target) in symlinks {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_mode(0o777);
+            header.set_link_name(target).unwrap();
+            builder.append_data(&mut header, path, io::empty()).unwrap();
+        }
+
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn doesnt_write_through_symlinks_out_of_the_archive() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = root.path().join("outside");
+        let target_dir = root.path().join("target");
+        fs::create_dir_all(&outside).unwrap();
+
+        let archive = tar_gz(
+            &[
+                ("repo/x", outside.to_str().unwrap()),
+                ("repo/y", "../.."),
+                ("repo/a/b", "../../outside"),
+            ],
+            &[("repo/x/.bashrc", "evil"), ("repo/y/.bashrc", "evil")],
+        );
+
+        extract(Format::TarGz, Cursor::new(archive), &target_dir, None).unwrap();
+
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+        assert!(!target_dir.join("a/b").exists());
+        assert_eq!(
+            fs::read_to_string(target_dir.join("x/.bashrc")).unwrap(),
+            "evil"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_symlinks_inside_the_archive() {
+        let root = tempfile::tempdir().unwrap();
+        let target_dir = root.path().join("target");
+
+        let archive = tar_gz(
+            &[("repo/web/README.md", "../README.md")],
+            &[("repo/README.md", "readme")],
+        );
+
+        extract(Format::TarGz, Cursor::new(archive), &target_dir, None).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target_dir.join("web/README.md")).unwrap(),
+            "readme"
+        );
+    }
+
+    #[test]
+    fn link_stays_inside_allows_going_up_to_the_top() {
+        assert!(link_stays_inside(Path::new("a/b"), Path::new("../../c")));
+        assert!(link_stays_inside(Path::new(""), Path::new("./c/d")));
+    }
+
+    #[test]
+    fn link_stays_inside_rejects_escapes() {
+        assert!(!link_stays_inside(Path::new("a"), Path::new("../..")));
+        assert!(!link_stays_inside(Path::new("a"), Path::new("/etc")));
+        assert!(!link_stays_inside(
+            Path::new("a/b"),
+            Path::new("c/../../..")
+        ));
+        assert!(!link_stays_inside(Path::new("a"), Path::new("c/..")));
+    }
+
+    #[test]
+    fn strip_toplevel_removes_the_first_component() {
+        assert_eq!(
+            strip_toplevel(Path::new("redwood-main/__fixtures__/a.txt")),
+            Some(PathBuf::from("__fixtures__/a.txt"))
+        );
+        assert_eq!(strip_toplevel(Path::new("redwood-main")), None);
+        assert_eq!(strip_toplevel(Path::new("redwood-main/")), None);
+    }
+
+    #[test]
+    fn strip_toplevel_rejects_paths_outside_of_the_archive() {
+        assert_eq!(strip_toplevel(Path::new("redwood-main/../a.txt")), None);
+        assert_eq!(strip_toplevel(Path::new("redwood-main/a/../../b")), None);
+        assert_eq!(strip_toplevel(Path::new("../redwood-main/a.txt")), None);
+        assert_eq!(strip_toplevel(Path::new("/redwood-main/a.txt")), None);
+        assert_eq!(strip_toplevel(Path::new("/etc/passwd")), None);
+    }
+}
@@ -0,0 +1,237 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use tokio::sync::mpsc;
+
+use crate::archive::{self, Format};
+use crate::events;
+use crate::github;
+use crate::http::{self, FetchError};
+use crate::i18n::t;
+use crate::partial_download::PartialDownload;
+
+/// How many downloaded chunks can be waiting for the extraction
+const CHANNEL_CAPACITY: usize = 64;
+/// The size of the chunks what earlier attempts downloaded is read in
+const EXISTING_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Downloads the archive and extracts it into `target_dir` at the same time,
+/// instead of waiting for the whole download first. Returns the commit the
+/// archive was made from. With `only`, nothing outside of that dir is
+/// extracted.
+///
+/// The download is checked the same way as `archive::download` checks it,
+/// once it's complete. Failed attempts start over with an empty
+/// `target_dir`, but resume the download itself where it stopped
+pub fn download_and_extract(
+    url: &str,
+    sha256: Option<&str>,
+    format: Format,
+    target_dir: &Path,
+    only: Option<&Path>,
+) -> Option<String> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to start the async runtime");
+
+    http::with_retries(&t!("download.action"), || {
+        clear_dir(target_dir);
+        runtime.block_on(attempt(url, sha256, format, target_dir, only))
+    })
+}
+
+async fn attempt(
+    url: &str,
+    sha256: Option<&str>,
+    format: Format,
+    target_dir: &Path,
+    only: Option<&Path>,
+) -> Result<Option<String>, FetchError> {
+    let mut partial = PartialDownload::open(url);
+    let mut response = github::get_async(url, partial.range_headers()).await?;
+    partial.begin(response.status(), response.headers())?;
+
+    let content_type = http::content_type(response.headers());
+    let mut chunks = Chunks {
+        existing: partial.existing().map_err(retryable)?,
+        partial,
+    };
+
+    // The first chunk is needed to tell the format
+    let first_chunk = chunks.next(&mut response).await?.unwrap_or_default();
+    let format = format.detect(content_type.as_deref(), &first_chunk);
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let target = target_dir.to_path_buf();
+    let only = only.map(Path::to_path_buf);
+    let extraction = tokio::task::spawn_blocking(move || {
+        archive::extract(format, ChunkReader::new(rx), &target, only.as_deref())
+    });
+
+    let mut hasher = Sha256::new();
+    let mut received = 0;
+    let mut chunk = Some(first_chunk);
+    let mut extraction_stopped = false;
+
+    while let Some(bytes) = chunk {
+        hasher.update(&bytes);
+        received += bytes.len() as u64;
+        events::progress(received, chunks.partial.total());
+
+        // The extraction only stops early if it fails, and then there's no
+        // point in downloading the rest
+        if tx.send(bytes).await.is_err() {
+            extraction_stopped = true;
+            break;
+        }
+
+        chunk = match chunks.next(&mut response).await {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                // The retry clears `target_dir` and extracts into it again,
+                // so the extraction has to be done with it first
+                drop(tx);
+                extraction.await.ok();
+                return Err(e);
+            }
+        };
+    }
+
+    drop(tx);
+    let extracted = extraction.await.expect("Extraction panicked");
+    let mut partial = chunks.partial;
+
+    tracing::debug!("Downloaded {received} bytes");
+
+    // The extraction failed before the download was done, so the download
+    // didn't cause it. Like a full disk, or an entry that can't be written.
+    // Retrying would only fail the same way
+    if extraction_stopped {
+        if let Err(e) = extracted {
+            partial.discard();
+            return Err(FetchError::Permanent(t!(
+                "download.extract_failed",
+                format = format.extension(),
+                error = e
+            )));
+        }
+    }
+
+    // A truncated download explains any other extraction error, so check
+    // that first. It's the only kind of failure that can be resumed
+    match partial.total() {
+        Some(expected) if expected != received => {
+            if received > expected {
+                partial.discard();
+            }
+
+            return Err(FetchError::Retryable(t!(
+                "download.truncated",
+                expected = expected,
+                received = received
+            )));
+        }
+        // Nothing left to resume. If the archive is corrupted, the next
+        // attempt has to start over anyway
+        _ => partial.discard(),
+    }
+
+    let commit = extracted.map_err(|e| {
+        FetchError::Retryable(t!(
+            "download.invalid",
+            format = format.extension(),
+            error = e
+        ))
+    })?;
+
+    if let Some(expected) = sha256 {
+        archive::compare_sha256(&format!("{:x}", hasher.finalize()), expected)?;
+    }
+
+    Ok(commit)
+}
+
+/// The whole download, chunk by chunk. What earlier attempts downloaded
+/// comes first, then the response's body, which is kept for resuming
+struct Chunks {
+    existing: Option<fs::File>,
+    partial: PartialDownload,
+}
+
+impl Chunks {
+    async fn next(
+        &mut self,
+        response: &mut reqwest::Response,
+    ) -> Result<Option<Vec<u8>>, FetchError> {
+        if let Some(existing) = &mut self.existing {
+            let mut chunk = vec![0; EXISTING_CHUNK_SIZE];
+            let read = existing.read(&mut chunk).map_err(retryable)?;
+
+            if read > 0 {
+                chunk.truncate(read);
+                return Ok(Some(chunk));
+            }
+
+            self.existing = None;
+        }
+
+        let Some(bytes) = response.chunk().await? else {
+            return Ok(None);
+        };
+
+        self.partial.append(&bytes);
+
+        Ok(Some(bytes.to_vec()))
+    }
+}
+
+fn retryable(e: io::Error) -> FetchError {
+    FetchError::Retryable(e.to_string())
+}
+
+fn clear_dir(dir: &Path) {
+    if dir.exists() {
+        fs::remove_dir_all(dir).expect("Failed to clear temp dir");
+    }
+    fs::create_dir_all(dir).expect("Failed to create temp dir");
+}
+
+/// Reads the downloaded chunks as one stream
+struct ChunkReader {
+    rx: mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl ChunkReader {
+    fn new(rx: mpsc::Receiver<Vec<u8>>) -> Self {
+        ChunkReader {
+            rx,
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                // The download is done
+                None => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+
+        Ok(n)
+    }
+}
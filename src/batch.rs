@@ -0,0 +1,117 @@
+use clap::{CommandFactory, FromArgMatches};
+use std::fs;
+use std::path::Path;
+
+use crate::i18n::t;
+use crate::{config_file, theme, Args};
+
+/// Reads the projects to create from a manifest like
+///
+/// ```toml
+/// redwood-tag = "canary"
+///
+/// [[project]]
+/// dir = "workshop/alice"
+///
+/// [[project]]
+/// dir = "workshop/bob"
+/// template = "test-project-rsc-kitchen-sink"
+/// skip-install = true
+/// ```
+///
+/// Each project's settings are the same as the command line flags for
+/// creating a project. Settings outside of a `[[project]]` apply to all of
+/// them. The projects are always created without asking any questions
+pub fn read_manifest(path: &Path) -> Vec<Args> {
+    let contents = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!(
+            "{}",
+            theme::error(t!(
+                "errors.cant_read",
+                file = path.to_string_lossy(),
+                error = e
+            ))
+        );
+        std::process::exit(1);
+    });
+
+    let mut manifest: toml::Table = toml::from_str(&contents).unwrap_or_else(|e| {
+        eprintln!(
+            "{}",
+            theme::error(t!(
+                "errors.cant_parse",
+                file = path.to_string_lossy(),
+                error = e
+            ))
+        );
+        std::process::exit(1);
+    });
+
+    let projects = match manifest.remove("project") {
+        Some(toml::Value::Array(projects)) if !projects.is_empty() => projects,
+        _ => {
+            eprintln!(
+                "{}",
+                theme::error(t!("batch.no_projects", file = path.to_string_lossy()))
+            );
+            std::process::exit(1);
+        }
+    };
+
+    projects
+        .into_iter()
+        .enumerate()
+        .map(|(i, project)| {
+            let toml::Value::Table(project) = project else {
+                eprintln!("{}", theme::error(t!("batch.not_a_table", number = i + 1)));
+                std::process::exit(1);
+            };
+
+            let mut settings = manifest.clone();
+            settings.extend(project);
+
+            parse_project(settings).unwrap_or_else(|e| {
+                eprintln!(
+                    "{}",
+                    theme::error(t!("batch.invalid_project", number = i + 1))
+                );
+                e.exit();
+            })
+        })
+        .collect()
+}
+
+/// Turns a project's settings into command line arguments, and parses those
+/// like we do the real ones. The config file is applied as well
+fn parse_project(mut settings: toml::Table) -> Result<Args, clap::Error> {
+    let Some(toml::Value::String(dir)) = settings.remove("dir") else {
+        return Err(Args::command().error(
+            clap::error::ErrorKind::MissingRequiredArgument,
+            t!("batch.dir_required"),
+        ));
+    };
+
+    let mut argv = vec![env!("CARGO_PKG_NAME").to_owned(), "--yes".to_owned()];
+
+    for (key, value) in settings {
+        let flag = format!("--{}", key.replace('_', "-"));
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+
+        for value in values {
+            match value {
+                toml::Value::Boolean(true) => argv.push(flag.clone()),
+                toml::Value::Boolean(false) => {}
+                toml::Value::String(s) => argv.push(format!("{flag}={s}")),
+                value => argv.push(format!("{flag}={value}")),
+            }
+        }
+    }
+
+    argv.extend(["--".to_owned(), dir]);
+
+    let matches = config_file::apply(Args::command()).try_get_matches_from(argv)?;
+    Args::from_arg_matches(&matches)
+}
@@ -0,0 +1,196 @@
+use serde_json::Value;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::fetch::TEMP_DIR_PREFIX;
+use crate::i18n::t;
+use crate::{confirm, fs_util, lock, provenance, theme};
+
+/// Temp dirs that have been touched more recently than this might belong to
+/// a run that's still going
+const MIN_TEMP_DIR_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Removes temp dirs left behind by runs that crashed, and our caches. With
+/// `project_dir`, a project that wasn't completely set up is removed as well
+pub fn run(project_dir: Option<&str>, yes: bool) {
+    remove_stale_temp_dirs();
+    clear_cache();
+
+    if let Some(project_dir) = project_dir {
+        remove_partial_project(&fs_util::expand_tilde(project_dir), yes);
+    }
+}
+
+fn remove_stale_temp_dirs() {
+    let temp_dir = std::env::temp_dir();
+    let Ok(entries) = fs::read_dir(&temp_dir) else {
+        return;
+    };
+
+    let mut removed = 0;
+    let mut skipped = 0;
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if !name.to_string_lossy().starts_with(TEMP_DIR_PREFIX) {
+            continue;
+        }
+
+        let age = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+
+        if age.is_some_and(|age| age < MIN_TEMP_DIR_AGE) {
+            skipped += 1;
+            continue;
+        }
+
+        tracing::debug!("Removing {}", entry.path().to_string_lossy());
+
+        match fs::remove_dir_all(entry.path()) {
+            Ok(()) => removed += 1,
+            Err(e) => eprintln!(
+                "{}",
+                theme::warning(t!(
+                    "clean.cant_remove",
+                    path = entry.path().to_string_lossy(),
+                    error = e
+                ))
+            ),
+        }
+    }
+
+    let temp_dir = temp_dir.to_string_lossy();
+    if removed == 1 {
+        println!("{}", t!("clean.removed_temp_dir", dir = temp_dir));
+    } else {
+        println!(
+            "{}",
+            t!("clean.removed_temp_dirs", count = removed, dir = temp_dir)
+        );
+    }
+
+    if skipped > 0 {
+        println!("{}", t!("clean.kept_temp_dirs", count = skipped));
+    }
+}
+
+/// Removes everything in the cache dir but the locks, while holding all of
+/// them so no other run is using the cache at the same time
+fn clear_cache() {
+    let Some(cache_dir) = fs_util::cache_dir().filter(|dir| dir.exists()) else {
+        return;
+    };
+
+    let Some(_locks) = lock::try_lock_all() else {
+        eprintln!(
+            "{}",
+            theme::warning(t!("clean.cache_in_use", dir = cache_dir.to_string_lossy()))
+        );
+        return;
+    };
+
+    match fs_util::remove_dir_contents_except(&cache_dir, &[lock::LOCKS_DIR_NAME.into()]) {
+        Ok(()) => println!(
+            "{}",
+            t!("clean.cleared_cache", dir = cache_dir.to_string_lossy())
+        ),
+        Err(e) => eprintln!(
+            "{}",
+            theme::warning(t!(
+                "clean.cant_clear_cache",
+                dir = cache_dir.to_string_lossy(),
+                error = e
+            ))
+        ),
+    }
+}
+
+/// Removes a project we created, but never installed the dependencies of.
+/// That's what's left when something goes wrong halfway, but also what
+/// `--skip-install` gives you, so we ask first
+fn remove_partial_project(project_dir: &Path, yes: bool) {
+    let display_dir = project_dir.to_string_lossy();
+
+    let Some(json) = provenance::read(project_dir) else {
+        eprintln!(
+            "{}",
+            theme::error(t!(
+                "clean.not_ours",
+                dir = display_dir,
+                file = provenance::FILE_NAME
+            ))
+        );
+        std::process::exit(1);
+    };
+
+    if is_installed(project_dir) {
+        eprintln!("{}", theme::error(t!("clean.complete", dir = display_dir)));
+        eprintln!("{}", t!("clean.complete_hint"));
+        std::process::exit(1);
+    }
+
+    println!(
+        "{}",
+        t!(
+            "clean.partial",
+            dir = display_dir,
+            source = provenance::describe(&json)
+        )
+    );
+
+    // Projects created in an existing directory, like with `.`, only have
+    // what we put in them removed
+    let existing_files = json
+        .get("existing_files")
+        .and_then(Value::as_array)
+        .map(|files| {
+            files
+                .iter()
+                .filter_map(Value::as_str)
+                .map(OsString::from)
+                .collect::<Vec<_>>()
+        });
+
+    let question = match &existing_files {
+        Some(files) if !files.is_empty() => t!(
+            "clean.remove_contents_question",
+            dir = display_dir,
+            files = files
+                .iter()
+                .map(|file| file.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => t!("clean.remove_question", dir = display_dir),
+    };
+
+    if !yes && !confirm(&question) {
+        println!("{}", t!("clean.kept", dir = display_dir));
+        return;
+    }
+
+    match &existing_files {
+        Some(files) => {
+            fs_util::remove_dir_contents_except(project_dir, files)
+                .expect("Failed to remove the project");
+            println!("{}", t!("clean.removed_contents", dir = display_dir));
+        }
+        None => {
+            fs::remove_dir_all(project_dir).expect("Failed to remove directory");
+            println!("{}", t!("clean.removed", dir = display_dir));
+        }
+    }
+}
+
+/// Whether the project's dependencies have been installed. Yarn's Plug'n'Play
+/// doesn't create `node_modules`, but does leave its own files behind
+fn is_installed(project_dir: &Path) -> bool {
+    ["node_modules", ".pnp.cjs", ".yarn/install-state.gz"]
+        .iter()
+        .any(|path| project_dir.join(path).exists())
+}
@@ -0,0 +1,214 @@
+use lazy_static::lazy_static;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::fs_util;
+use crate::i18n::t;
+
+lazy_static! {
+    static ref CLEANUP: Mutex<Cleanup> = Mutex::new(Cleanup {
+        temp_dirs: Vec::new(),
+        children: Vec::new(),
+        partial_project: None,
+        rollback: true,
+        keep_temp: false,
+        forward_interrupts: false,
+    });
+}
+
+/// Everything that should be removed if the user aborts with Ctrl-C
+struct Cleanup {
+    temp_dirs: Vec<PathBuf>,
+    /// Running child processes. Each is the leader of its own process group
+    children: Vec<u32>,
+    /// The project directory while it's still being set up
+    partial_project: Option<PartialProject>,
+    rollback: bool,
+    /// Leave temp dirs in place for inspection, no matter how we exit
+    keep_temp: bool,
+    /// Let child processes handle Ctrl-C on their own, and don't exit
+    forward_interrupts: bool,
+}
+
+/// Installs a Ctrl-C handler that removes temp dirs and, if `rollback` is
+/// true, the partially created project before exiting.
+///
+/// Child processes run in their own process groups, so the interrupt is
+/// passed on to them
+pub fn install_handler(rollback: bool) {
+    CLEANUP.lock().unwrap().rollback = rollback;
+
+    ctrlc::set_handler(interrupt).expect("Failed to set Ctrl-C handler");
+}
+
+/// What Ctrl-C does: cleans up and exits, unless the interrupt is left to
+/// a child. Also called by the TUI, which gets Ctrl-C as a key press
+pub fn interrupt() {
+    if CLEANUP.lock().unwrap().forward_interrupts {
+        return;
+    }
+
+    #[cfg(feature = "tui")]
+    crate::tui::stop(true);
+
+    eprintln!();
+    eprintln!("{}", t!("cleanup.interrupted"));
+    interrupt_children();
+    run();
+    std::process::exit(130);
+}
+
+pub fn register_temp_dir(path: &Path) {
+    CLEANUP.lock().unwrap().temp_dirs.push(path.to_path_buf());
+}
+
+/// Removes the temp dir and stops tracking it. With `--keep-temp` it's only
+/// untracked
+pub fn remove_temp_dir(path: &Path) {
+    let mut cleanup = CLEANUP.lock().unwrap();
+
+    if cleanup.keep_temp {
+        eprintln!(
+            "{}",
+            t!("cleanup.keeping_temp_dir", dir = path.to_string_lossy())
+        );
+    } else {
+        fs::remove_dir_all(path).expect("Failed to remove temp dir");
+    }
+
+    cleanup.temp_dirs.retain(|temp_dir| temp_dir != path);
+}
+
+pub fn keep_temp_dirs() {
+    CLEANUP.lock().unwrap().keep_temp = true;
+}
+
+/// Removes all temp dirs that are still around, unless `--keep-temp` is
+/// given. Used when exiting early because something went wrong
+pub fn remove_temp_dirs() {
+    let mut cleanup = CLEANUP.lock().unwrap();
+    let keep_temp = cleanup.keep_temp;
+
+    for temp_dir in cleanup.temp_dirs.drain(..) {
+        if keep_temp {
+            eprintln!(
+                "{}",
+                t!("cleanup.keeping_temp_dir", dir = temp_dir.to_string_lossy())
+            );
+            continue;
+        }
+
+        tracing::debug!("Removing {}", temp_dir.to_string_lossy());
+
+        // Best effort. We're exiting anyway, so there's nothing more to do if
+        // this fails
+        fs::remove_dir_all(temp_dir).ok();
+    }
+}
+
+pub fn register_child(pid: u32) {
+    CLEANUP.lock().unwrap().children.push(pid);
+}
+
+pub fn unregister_child(pid: u32) {
+    CLEANUP
+        .lock()
+        .unwrap()
+        .children
+        .retain(|child| *child != pid);
+}
+
+/// Kills the child and everything it started
+pub fn kill_child(pid: u32) {
+    tracing::debug!("Killing process group {pid}");
+
+    #[cfg(unix)]
+    // SAFETY: `kill` has no memory safety requirements. A negative pid
+    // targets the process group
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+
+    #[cfg(windows)]
+    std::process::Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .output()
+        .ok();
+}
+
+fn interrupt_children() {
+    #[cfg(unix)]
+    for pid in CLEANUP.lock().unwrap().children.iter() {
+        // SAFETY: See `kill_child`
+        unsafe {
+            libc::kill(-(*pid as libc::pid_t), libc::SIGINT);
+        }
+    }
+}
+
+struct PartialProject {
+    path: PathBuf,
+    /// What was in the directory before the project was put in it, like a
+    /// README or `.git`. `None` if we created the directory
+    existing: Option<Vec<OsString>>,
+}
+
+/// Rolling back removes the project in `path`. If the directory already
+/// exists, only what's put in it from now on is removed
+pub fn register_partial_project(path: &Path) {
+    CLEANUP.lock().unwrap().partial_project = Some(PartialProject {
+        path: path.to_path_buf(),
+        existing: fs_util::entry_names(path),
+    });
+}
+
+/// Marks the project as fully set up, so it's kept even if the user
+/// interrupts after this point
+pub fn project_completed() {
+    CLEANUP.lock().unwrap().partial_project = None;
+}
+
+/// From now on Ctrl-C is only handled by child processes (which get it from
+/// the terminal as they're in the same process group). Used when handing over
+/// the terminal to a long running child, like a dev server
+pub fn forward_interrupts() {
+    CLEANUP.lock().unwrap().forward_interrupts = true;
+}
+
+fn run() {
+    remove_temp_dirs();
+
+    let mut cleanup = CLEANUP.lock().unwrap();
+
+    if let Some(project) = cleanup.partial_project.take() {
+        if cleanup.rollback {
+            eprintln!(
+                "{}",
+                t!(
+                    "cleanup.removing_project",
+                    dir = project.path.to_string_lossy()
+                )
+            );
+            remove_partial_project(&project);
+        } else {
+            eprintln!(
+                "{}",
+                t!(
+                    "cleanup.partial_project",
+                    dir = project.path.to_string_lossy()
+                )
+            );
+        }
+    }
+}
+
+fn remove_partial_project(project: &PartialProject) {
+    let Some(existing) = &project.existing else {
+        fs::remove_dir_all(&project.path).ok();
+        return;
+    };
+
+    fs_util::remove_dir_contents_except(&project.path, existing).ok();
+}
@@ -0,0 +1,235 @@
+use crate::i18n::t;
+use crate::package_manager::PackageManager;
+
+/// The `--commit-message`, followed by the snapshot as git trailers
+pub const DEFAULT: &str = "{message}\n\n{trailers}";
+
+/// What can be used in a `--commit-template`, as `{name}`
+const PLACEHOLDERS: [&str; 10] = [
+    "message",
+    "redwood_version",
+    "source",
+    "source_commit",
+    "template",
+    "node_version",
+    "package_manager",
+    "package_manager_version",
+    "tool_version",
+    "trailers",
+];
+
+/// What the project was generated from, and with, for the initial commit
+/// message. Anyone cloning the project later can see it in `git log`
+pub struct Snapshot<'a> {
+    /// The `--commit-message`
+    pub message: &'a str,
+    pub redwood_version: Option<&'a str>,
+    pub source: String,
+    pub source_commit: Option<&'a str>,
+    pub template: &'a str,
+    pub node_version: Option<String>,
+    pub package_manager: PackageManager,
+    pub package_manager_version: Option<&'a str>,
+}
+
+impl Snapshot<'_> {
+    fn tool(&self) -> String {
+        format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+    }
+
+    /// Like `yarn 4.5.0`
+    fn package_manager_with_version(&self) -> Option<String> {
+        self.package_manager_version
+            .map(|version| format!("{} {version}", self.package_manager.name()))
+    }
+
+    /// The value of each placeholder, except for `trailers`. `None` if it
+    /// isn't known, like the Redwood version with `--skip-version-bump`
+    fn values(&self) -> [(&'static str, Option<String>); 9] {
+        [
+            ("message", Some(self.message.to_owned())),
+            ("redwood_version", self.redwood_version.map(str::to_owned)),
+            ("source", Some(self.source.clone())),
+            ("source_commit", self.source_commit.map(str::to_owned)),
+            ("template", Some(self.template.to_owned())),
+            ("node_version", self.node_version.clone()),
+            (
+                "package_manager",
+                Some(self.package_manager.name().to_owned()),
+            ),
+            (
+                "package_manager_version",
+                self.package_manager_version.map(str::to_owned),
+            ),
+            ("tool_version", Some(self.tool())),
+        ]
+    }
+
+    /// `Key: value` lines, like `Redwood-Version: 8.0.0-canary.1234`, for
+    /// everything that's known
+    fn trailers(&self) -> String {
+        [
+            ("Redwood-Version", self.redwood_version.map(str::to_owned)),
+            ("Source", Some(self.source.clone())),
+            ("Source-Commit", self.source_commit.map(str::to_owned)),
+            ("Template", Some(self.template.to_owned())),
+            ("Node-Version", self.node_version.clone()),
+            ("Package-Manager", self.package_manager_with_version()),
+            ("Generated-By", Some(self.tool())),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| format!("{key}: {value}")))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+}
+
+/// Checks that `template` only uses placeholders we know, for clap
+pub fn parse(template: &str) -> Result<String, String> {
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + end];
+
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(t!(
+                "errors.unknown_placeholder",
+                placeholder = format!("{{{name}}}"),
+                available = PLACEHOLDERS
+                    .iter()
+                    .map(|name| format!("{{{name}}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+
+    Ok(template.to_owned())
+}
+
+/// Fills in the placeholders in `template`. Unknown values become
+/// `unknown`, and are left out of `{trailers}`. Each placeholder is
+/// replaced where it is in the template, so braces in the values, like in a
+/// commit message, are never taken for placeholders
+pub fn render(template: &str, snapshot: &Snapshot) -> String {
+    let values = snapshot.values();
+    let value_of = |name: &str| -> Option<String> {
+        if name == "trailers" {
+            return Some(snapshot.trailers());
+        }
+
+        values
+            .iter()
+            .find(|(placeholder, _)| *placeholder == name)
+            .map(|(_, value)| value.clone().unwrap_or_else(|| "unknown".to_owned()))
+    };
+
+    let mut message = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let placeholder = rest
+            .find('}')
+            .and_then(|end| value_of(&rest[1..end]).map(|value| (value, end)));
+
+        match placeholder {
+            Some((value, end)) => {
+                message.push_str(&value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                message.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    message.push_str(rest);
+
+    message.trim_end().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(message: &str) -> Snapshot<'_> {
+        Snapshot {
+            message,
+            redwood_version: Some("8.0.0-canary.1234"),
+            source: "redwoodjs/redwood@main".to_owned(),
+            source_commit: None,
+            template: "test-project-rsc-kitchen-sink",
+            node_version: Some("v20.11.0".to_owned()),
+            package_manager: PackageManager::Yarn,
+            package_manager_version: Some("4.5.0"),
+        }
+    }
+
+    #[test]
+    fn render_fills_in_placeholders() {
+        let message = render(
+            "{template} on {redwood_version} from {source_commit}",
+            &snapshot("Initial commit"),
+        );
+
+        assert_eq!(
+            message,
+            "test-project-rsc-kitchen-sink on 8.0.0-canary.1234 from unknown"
+        );
+    }
+
+    #[test]
+    fn render_leaves_unknown_values_out_of_the_trailers() {
+        let message = render(DEFAULT, &snapshot("Initial commit"));
+
+        assert!(message.starts_with("Initial commit\n\nRedwood-Version: 8.0.0-canary.1234\n"));
+        assert!(message.contains("\nPackage-Manager: yarn 4.5.0\n"));
+        assert!(!message.contains("Source-Commit"));
+    }
+
+    #[test]
+    fn render_keeps_placeholders_in_the_message() {
+        let message = render(DEFAULT, &snapshot("Add {trailers} and {template"));
+
+        assert!(message.starts_with("Add {trailers} and {template\n\nRedwood-Version: "));
+        assert_eq!(message.matches("Redwood-Version").count(), 1);
+    }
+
+    #[test]
+    fn render_keeps_unclosed_and_unknown_braces() {
+        let snapshot = snapshot("Initial commit");
+
+        assert_eq!(render("{message} {", &snapshot), "Initial commit {");
+        assert_eq!(render("{message", &snapshot), "{message");
+        assert_eq!(
+            render("{nope} {message}", &snapshot),
+            "{nope} Initial commit"
+        );
+    }
+
+    #[test]
+    fn parse_accepts_known_placeholders() {
+        assert_eq!(parse(DEFAULT).as_deref(), Ok(DEFAULT));
+        assert!(parse("{message} ({tool_version})").is_ok());
+    }
+
+    #[test]
+    fn parse_rejects_unknown_placeholders() {
+        assert!(parse("{message} {nope}").is_err());
+        assert!(parse("{}").is_err());
+    }
+
+    #[test]
+    fn parse_ignores_an_unclosed_brace() {
+        assert!(parse("{message} {").is_ok());
+        assert!(parse("{nope").is_ok());
+    }
+}
@@ -0,0 +1,145 @@
+use clap::Command;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::i18n::t;
+
+const ENV_PREFIX: &str = "RW_QUICKSTART_";
+
+/// Layers defaults from the config file and `RW_QUICKSTART_*` env vars under
+/// the command line flags.
+///
+/// Values from the config file become the args' default values, and every
+/// flag can also be set with an env var named after it, like
+/// `RW_QUICKSTART_PACKAGE_MANAGER`. clap then gives us the precedence we want:
+/// command line flags, then env vars, then the config file, then the
+/// built-in defaults
+pub fn apply(mut cmd: Command) -> Command {
+    let ids: Vec<String> = cmd
+        .get_arguments()
+        .filter(|arg| arg.get_long().is_some())
+        .map(|arg| arg.get_id().to_string())
+        .collect();
+
+    for id in &ids {
+        let env = format!("{ENV_PREFIX}{}", id.to_uppercase());
+        cmd = cmd.mut_arg(id, |arg| arg.env(env));
+    }
+
+    let Some(path) = config_path() else {
+        return cmd;
+    };
+
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return cmd;
+    };
+
+    let table: toml::Table = toml::from_str(&contents).unwrap_or_else(|e| {
+        eprintln!(
+            "{}",
+            t!(
+                "errors.cant_parse",
+                file = path.to_string_lossy(),
+                error = e
+            )
+        );
+        std::process::exit(1);
+    });
+
+    for (key, value) in table {
+        // Accept both `package-manager` and `package_manager`
+        let id = key.replace('-', "_");
+
+        if !ids.contains(&id) {
+            eprintln!(
+                "{}",
+                t!(
+                    "config_file.unknown_setting",
+                    key = key,
+                    file = path.to_string_lossy()
+                )
+            );
+            continue;
+        }
+
+        let values = match value {
+            toml::Value::Array(items) => items.into_iter().map(scalar).collect(),
+            value => scalar(value).map(|value| vec![value]),
+        };
+
+        let Some(values) = values else {
+            eprintln!(
+                "{}",
+                t!(
+                    "config_file.unsupported_value",
+                    key = key,
+                    file = path.to_string_lossy()
+                )
+            );
+            continue;
+        };
+
+        cmd = cmd.mut_arg(&id, |arg| arg.default_values(values));
+    }
+
+    cmd
+}
+
+/// A config file value as it would be given on the command line
+fn scalar(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s),
+        toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+            Some(value.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Sets `key` in the config file, creating it if needed. Returns the file's
+/// path
+pub fn set(key: &str, value: toml::Value) -> PathBuf {
+    let Some(path) = config_path() else {
+        eprintln!("{}", t!("config_file.no_config_dir"));
+        std::process::exit(1);
+    };
+
+    let mut table: toml::Table = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default();
+
+    table.insert(key.to_owned(), value);
+
+    let result = fs::create_dir_all(path.parent().unwrap())
+        .and_then(|()| fs::write(&path, table.to_string()));
+
+    if let Err(e) = result {
+        eprintln!(
+            "{}",
+            t!(
+                "errors.cant_write",
+                file = path.to_string_lossy(),
+                error = e
+            )
+        );
+        std::process::exit(1);
+    }
+
+    path
+}
+
+/// `RW_QUICKSTART_CONFIG` if set, otherwise
+/// `$XDG_CONFIG_HOME/rw-rsc-quickstart/config.toml`, falling back to
+/// `~/.config/rw-rsc-quickstart/config.toml`
+pub fn config_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var(format!("{ENV_PREFIX}CONFIG")) {
+        return Some(PathBuf::from(path));
+    }
+
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+
+    Some(config_dir.join("rw-rsc-quickstart").join("config.toml"))
+}
@@ -0,0 +1,175 @@
+use reqwest::Url;
+use serde_json::json;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::env_file;
+use crate::events;
+use crate::exec::{display_command, try_exec_streaming_in_with_env};
+use crate::i18n::t;
+use crate::package_manager::PackageManager;
+use crate::{theme, Config};
+
+/// Where Redwood keeps the Prisma schema
+const SCHEMA_PATH: &str = "api/db/schema.prisma";
+/// Redwood's own default, relative to the schema
+const SQLITE_URL: &str = "file:./dev.db";
+
+/// The database to set up with `--db`
+#[derive(Clone)]
+pub enum Database {
+    /// A file in the project, which needs nothing else to be running
+    Sqlite,
+    /// A PostgreSQL server, at this url
+    Postgres(Url),
+}
+
+impl Database {
+    /// Parses `sqlite`, or a `postgres://` or `postgresql://` url
+    pub fn parse(value: &str) -> Result<Database, String> {
+        if value.eq_ignore_ascii_case("sqlite") {
+            return Ok(Database::Sqlite);
+        }
+
+        let url =
+            Url::parse(value).map_err(|e| t!("database.invalid", value = value, error = e))?;
+
+        match url.scheme() {
+            "postgres" | "postgresql" => Ok(Database::Postgres(url)),
+            scheme => Err(t!("database.unsupported", scheme = scheme)),
+        }
+    }
+
+    /// The provider in the Prisma schema's datasource
+    fn provider(&self) -> &'static str {
+        match self {
+            Database::Sqlite => "sqlite",
+            Database::Postgres(_) => "postgresql",
+        }
+    }
+
+    fn url(&self) -> &str {
+        match self {
+            Database::Sqlite => SQLITE_URL,
+            Database::Postgres(url) => url.as_str(),
+        }
+    }
+}
+
+/// Leaves out the password, so it doesn't end up in logs
+impl fmt::Debug for Database {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Database::Sqlite => f.write_str("Sqlite"),
+            Database::Postgres(url) => {
+                let mut url = url.clone();
+                if url.password().is_some() {
+                    url.set_password(Some("***")).ok();
+                }
+                write!(f, "Postgres({url})")
+            }
+        }
+    }
+}
+
+/// Points the project at `database` and runs its migrations, which also
+/// seeds it, as Redwood runs its seed script after `migrate dev` creates a
+/// database.
+///
+/// A failing migration doesn't stop the rest of the setup. The project is
+/// fine, it's the database that needs looking into, so it's reported as
+/// that along with how to try again
+pub fn setup(database: &Database, package_manager: PackageManager, project_dir: &Path) {
+    let phase = events::start("database");
+    let migrate = [
+        package_manager.exec_prefix(),
+        &["rw", "prisma", "migrate", "dev", "--name", "init"],
+    ]
+    .concat();
+
+    if Config::is_dry_run() {
+        events::status(t!(
+            "dry_run.database",
+            schema = SCHEMA_PATH,
+            provider = database.provider()
+        ));
+        events::status(t!(
+            "dry_run.run",
+            command = display_command(&migrate),
+            dir = project_dir.to_string_lossy()
+        ));
+        phase.finish(json!({ "provider": database.provider() }));
+        return;
+    }
+
+    set_provider(&project_dir.join(SCHEMA_PATH), database.provider());
+    env_file::set(project_dir, "DATABASE_URL", database.url());
+
+    events::status(t!("database.setting_up", provider = database.provider()));
+
+    let migrated = match try_exec_streaming_in_with_env(&migrate, project_dir, &[]) {
+        Ok(_) => true,
+        Err(failed) => {
+            eprintln!("{}", theme::error(t!("database.migration_failed")));
+            eprintln!("{}", failed.summary());
+            eprintln!("{}", failed.details(!Config::shows_output()));
+
+            if let Database::Postgres(url) = database {
+                eprintln!(
+                    "{}",
+                    t!(
+                        "database.check_server",
+                        host = url.host_str().unwrap_or_default()
+                    )
+                );
+            }
+
+            eprintln!(
+                "{}",
+                t!("database.try_again", command = display_command(&migrate))
+            );
+            false
+        }
+    };
+
+    phase.finish(json!({ "provider": database.provider(), "migrated": migrated }));
+}
+
+/// Sets the provider of the schema's datasource. The templates come with
+/// SQLite
+fn set_provider(schema_path: &Path, provider: &str) {
+    let Ok(schema) = fs::read_to_string(schema_path) else {
+        eprintln!(
+            "{}",
+            theme::warning(t!("database.no_schema", schema = SCHEMA_PATH))
+        );
+        return;
+    };
+
+    let mut in_datasource = false;
+    let lines: Vec<String> = schema
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+
+            if trimmed.starts_with("datasource ") {
+                in_datasource = true;
+            } else if trimmed.starts_with('}') {
+                in_datasource = false;
+            } else if in_datasource && trimmed.starts_with("provider") {
+                let indent = &line[..line.len() - trimmed.len()];
+                return format!("{indent}provider = \"{provider}\"");
+            }
+
+            line.to_owned()
+        })
+        .collect();
+
+    let mut contents = lines.join("\n");
+    if schema.ends_with('\n') {
+        contents.push('\n');
+    }
+
+    fs::write(schema_path, contents).expect("Failed to write the Prisma schema");
+}
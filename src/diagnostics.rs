@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Write};
+use std::sync::{Mutex, OnceLock};
+
+use crate::i18n::t;
+use crate::{cleanup, telemetry, theme, timing};
+
+/// Where the diagnostics are written, relative to the current directory
+const DEBUG_LOG: &str = "quickstart-debug.log";
+/// How many lines of the log to keep around for the debug log
+const MAX_LINES: usize = 1000;
+
+static RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+/// What's being run. The debug log is only written once this is set
+static CONTEXT: OnceLock<String> = OnceLock::new();
+
+/// Log output that's kept in memory, so it can be written to the debug log
+/// if something goes wrong
+pub struct RecentLines;
+
+impl Write for RecentLines {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut lines = RECENT_LINES.lock().unwrap();
+
+        for line in String::from_utf8_lossy(buf).lines() {
+            if lines.len() == MAX_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_owned());
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// From now on failures write a debug log with `context`, the versions of
+/// the tool and the OS, and the most recent log output, which includes the
+/// commands that were run and what they printed
+pub fn enable(context: String) {
+    if CONTEXT.set(context).is_err() {
+        return;
+    }
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // Give the terminal back before the panic is printed
+        #[cfg(feature = "tui")]
+        crate::tui::stop(true);
+        default_hook(info);
+        write_debug_log();
+    }));
+}
+
+/// Cleans up, writes the debug log, and exits with an error
+pub fn fail() -> ! {
+    #[cfg(feature = "tui")]
+    crate::tui::stop(true);
+    cleanup::remove_temp_dirs();
+    telemetry::report(false);
+    timing::write_profile(true);
+    write_debug_log();
+    std::process::exit(1);
+}
+
+fn write_debug_log() {
+    let Some(context) = CONTEXT.get() else {
+        return;
+    };
+
+    let mut log = format!(
+        "{} {} on {}-{}\n{context}\n\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+
+    for line in RECENT_LINES.lock().unwrap().iter() {
+        log.push_str(line);
+        log.push('\n');
+    }
+
+    match fs::write(DEBUG_LOG, log) {
+        Ok(()) => {
+            eprintln!();
+            eprintln!(
+                "{}",
+                t!("diagnostics.wrote_log", file = theme::warning(DEBUG_LOG))
+            );
+            eprintln!("{}", t!("diagnostics.attach_log"));
+        }
+        Err(e) => eprintln!(
+            "{}",
+            t!("diagnostics.cant_write_log", file = DEBUG_LOG, error = e)
+        ),
+    }
+}
@@ -0,0 +1,289 @@
+use semver_rs::satisfies;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::archive::Format;
+use crate::confirm;
+use crate::events::OutputFormat;
+use crate::exec::exec;
+use crate::http::{self, FetchError};
+use crate::i18n::t;
+use crate::node::{self, VersionManager};
+use crate::package_manager::{diagnose_yarn_installation, YarnProblem};
+use crate::preflight;
+use crate::provenance;
+use crate::registry::registry;
+use crate::source::Source;
+use crate::theme;
+use crate::yarns;
+use crate::{Config, MIN_NODE_VERSION};
+
+/// How a check turned out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Pass,
+    /// Not a problem for creating a project, but worth knowing about
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        }
+    }
+}
+
+/// The outcome of a single check, with what to do about it if it didn't pass
+#[derive(Debug)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    message: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, message: impl Into<String>) -> Check {
+        Check {
+            name,
+            status: Status::Pass,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Check {
+        Check {
+            name,
+            status: Status::Warn,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Check {
+        Check {
+            name,
+            status: Status::Fail,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "status": self.status.name(),
+            "message": self.message,
+            "hint": self.hint,
+        })
+    }
+}
+
+/// Runs all environment checks and reports on each of them, instead of
+/// stopping at the first problem like the regular checks do.
+///
+/// With `fix` we offer to fix the problems we know how to fix. With
+/// `--output json` a single report with every check is printed instead, for
+/// CI to gate on. Exits with an error if any check fails, but not for
+/// warnings
+pub fn run(fix: bool) {
+    let json_output = Config::output_format() == OutputFormat::Json;
+
+    let mut checks = Vec::new();
+    // Printed as they're done, as the network checks can take a while
+    let mut add = |check: Check| {
+        if !json_output {
+            print_check(&check);
+        }
+        checks.push(check);
+    };
+
+    add(check_node(fix));
+    add(check_yarn(fix));
+    add(check_git());
+    add(check_network(
+        "github",
+        &Source::default().archive_url(Format::Zip).unwrap(),
+    ));
+    add(check_network("registry", &registry().url));
+    add(check_disk_space());
+
+    let ok = checks.iter().all(|check| check.status != Status::Fail);
+
+    if json_output {
+        println!(
+            "{}",
+            json!({
+                "event": "doctor",
+                "ok": ok,
+                "checks": checks.iter().map(Check::to_json).collect::<Vec<_>>(),
+                "provenance": provenance::read(Path::new(".")),
+            })
+        );
+    } else {
+        print_provenance();
+
+        if ok {
+            println!("{}", t!("doctor.all_good"));
+        }
+    }
+
+    if !ok {
+        std::process::exit(1);
+    }
+}
+
+fn print_check(check: &Check) {
+    match check.status {
+        Status::Pass => println!("{}", theme::success(&check.message)),
+        Status::Warn => println!("{}", theme::warning(&check.message)),
+        Status::Fail => println!("{}", theme::failure(&check.message)),
+    }
+
+    if let Some(hint) = &check.hint {
+        for line in hint.lines() {
+            println!("  {line}");
+        }
+    }
+}
+
+fn check_node(fix: bool) -> Check {
+    let required = node::required_version(Some(Path::new(".")));
+
+    match node::version() {
+        Some(version) if satisfies(&version, MIN_NODE_VERSION, None).unwrap() => {
+            Check::pass("node", t!("doctor.node", version = version))
+        }
+        version => {
+            if fix && node::install_required(&required, true) {
+                return Check::pass("node", t!("doctor.node_installed", version = required));
+            }
+
+            let message = match version {
+                Some(version) => t!("node.too_old", version = version),
+                None => t!("node.not_found"),
+            };
+
+            Check::fail(
+                "node",
+                message,
+                node::guidance(&required, &VersionManager::detect()),
+            )
+        }
+    }
+}
+
+/// Tells what the project in the current directory was created from, if we
+/// created it
+fn print_provenance() {
+    let Some(json) = provenance::read(Path::new(".")) else {
+        return;
+    };
+
+    println!(
+        "{}",
+        t!("doctor.created_from", source = provenance::describe(&json))
+    );
+
+    if let Some(version) = json.get("redwood_version").and_then(|v| v.as_str()) {
+        println!("{}", t!("doctor.created_with", version = version));
+    }
+}
+
+fn check_yarn(fix: bool) -> Check {
+    match diagnose_yarn_installation() {
+        Ok(()) => Check::pass("yarn", t!("doctor.yarn")),
+        Err(YarnProblem::NotFound) if fix => {
+            if which::which("corepack").is_err() {
+                return Check::fail(
+                    "yarn",
+                    t!("doctor.corepack_not_found"),
+                    t!("doctor.corepack_not_found_hint"),
+                );
+            }
+
+            if !confirm(&t!("doctor.enable_yarn_question")) {
+                return yarn_problem(YarnProblem::NotFound);
+            }
+
+            exec(&["corepack", "enable"]);
+            exec(&["corepack", "install", "--global", "yarn@latest"]);
+
+            match diagnose_yarn_installation() {
+                Ok(()) => Check::pass("yarn", t!("doctor.yarn_enabled")),
+                Err(problem) => yarn_problem(problem),
+            }
+        }
+        Err(YarnProblem::CorepackShadowed(_) | YarnProblem::Multiple(_))
+            if fix && yarns::remove_extra_yarns() =>
+        {
+            match diagnose_yarn_installation() {
+                Ok(()) => Check::pass("yarn", t!("doctor.yarns_removed")),
+                Err(problem) => yarn_problem(problem),
+            }
+        }
+        Err(problem) => yarn_problem(problem),
+    }
+}
+
+fn yarn_problem(problem: YarnProblem) -> Check {
+    match problem {
+        YarnProblem::NotFound => Check::fail("yarn", t!("doctor.yarn_not_enabled"), problem.hint()),
+        YarnProblem::CorepackShadowed(_) => Check::fail(
+            "yarn",
+            t!("doctor.yarn_shadowed"),
+            format!("{}\n{}", problem.hint(), yarns::describe_all()),
+        ),
+        YarnProblem::Multiple(count) => Check::fail(
+            "yarn",
+            t!("doctor.yarn_multiple", count = count),
+            format!("{}\n{}", problem.hint(), yarns::describe_all()),
+        ),
+    }
+}
+
+fn check_git() -> Check {
+    match which::which("git") {
+        Ok(_) => Check::pass("git", exec(&["git", "--version"]).trim()),
+        Err(_) => Check::warn("git", t!("doctor.git_not_found"), t!("doctor.git_hint")),
+    }
+}
+
+fn check_network(name: &'static str, url: &str) -> Check {
+    tracing::debug!("Checking connection to {url}");
+
+    let response = http::fetcher().head(url, Duration::from_secs(10));
+
+    match response {
+        Ok(_) => Check::pass(name, t!("doctor.reachable", url = url)),
+        Err(FetchError::Retryable(e) | FetchError::Permanent(e)) => Check::fail(
+            name,
+            t!("doctor.unreachable", url = url, error = e),
+            t!("doctor.unreachable_hint"),
+        ),
+    }
+}
+
+/// Checks that a project with dependencies can be created in the current
+/// directory
+fn check_disk_space() -> Check {
+    let shortfalls = preflight::find_shortfalls(Path::new("."), true);
+
+    if shortfalls.is_empty() {
+        return Check::pass("disk_space", t!("doctor.disk_space"));
+    }
+
+    let message = shortfalls
+        .iter()
+        .map(preflight::Shortfall::describe)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Check::fail("disk_space", message, preflight::space_hint())
+}
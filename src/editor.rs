@@ -0,0 +1,61 @@
+use std::path::Path;
+use std::process::Stdio;
+
+use crate::events;
+use crate::exec;
+use crate::i18n::t;
+use crate::Config;
+
+/// Editor commands to look for, in this order, when no editor is given
+const KNOWN_EDITORS: [&str; 3] = ["code", "cursor", "webstorm"];
+
+/// Opens the project in an editor without waiting for it to close.
+///
+/// `editor` is the command to run, with the project dir as its last argument.
+/// Without an editor, the first of the known editors found in PATH is used
+pub fn open(editor: &str, project_dir: &Path) {
+    let command = if editor.is_empty() {
+        let detected = KNOWN_EDITORS.iter().find(|cmd| which::which(cmd).is_ok());
+
+        match detected {
+            Some(cmd) => cmd.to_string(),
+            None => {
+                eprintln!("{}", t!("editor.not_found"));
+                eprintln!("{}", t!("editor.not_found_hint"));
+                return;
+            }
+        }
+    } else {
+        editor.to_owned()
+    };
+
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        return;
+    };
+
+    if Config::is_dry_run() {
+        events::status(t!(
+            "dry_run.open",
+            dir = project_dir.to_string_lossy(),
+            command = command
+        ));
+        return;
+    }
+
+    events::status(t!("editor.opening", command = command));
+
+    // Not being able to open the editor shouldn't fail the whole run, the
+    // project has been created at this point
+    let result = exec::command(program)
+        .args(parts)
+        .arg(project_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    if let Err(e) = result {
+        eprintln!("{}", t!("editor.failed", command = command, error = e));
+    }
+}
@@ -0,0 +1,156 @@
+use dialoguer::Input;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::i18n::t;
+use crate::{diagnostics, events, theme};
+
+/// Values in `.env.example` that are only there to show what to fill in
+const PLACEHOLDERS: [&str; 4] = ["changeme", "change-me", "todo", "xxx"];
+
+/// Creates the project's `.env` from `.env.example`, so the dev server can
+/// start without any manual setup. Variables without a real value in either
+/// `.env.example` or `.env.defaults` are asked for if `interactive`, and
+/// listed otherwise.
+///
+/// An existing `.env` is left alone
+pub fn create(project_dir: &Path, interactive: bool) {
+    let env_path = project_dir.join(".env");
+    let example = fs::read_to_string(project_dir.join(".env.example")).ok();
+    let defaults = fs::read_to_string(project_dir.join(".env.defaults")).ok();
+
+    if env_path.exists() || (example.is_none() && defaults.is_none()) {
+        return;
+    }
+
+    // Redwood loads `.env.defaults` on its own, so anything set there is
+    // taken care of
+    let defined: HashSet<String> = defaults
+        .as_deref()
+        .map(|defaults| {
+            defaults
+                .lines()
+                .filter_map(parse_line)
+                .filter(|(_, value)| !looks_unset(value))
+                .map(|(key, _)| key.to_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let Some(example) = example else {
+        fs::write(&env_path, "# Overrides for the values in .env.defaults\n")
+            .expect("Failed to write .env");
+        return;
+    };
+
+    let mut missing = Vec::new();
+    let mut lines = Vec::new();
+
+    for line in example.lines() {
+        let Some((key, _)) = parse_line(line).filter(|(_, value)| looks_unset(value)) else {
+            lines.push(line.to_owned());
+            continue;
+        };
+
+        // An empty value would override the one in `.env.defaults`
+        if defined.contains(key) {
+            lines.push(format!("# {line}"));
+            continue;
+        }
+
+        let key = key.to_owned();
+
+        let value = if interactive {
+            prompt(&key)
+        } else {
+            String::new()
+        };
+
+        if value.is_empty() {
+            missing.push(key.clone());
+        }
+
+        lines.push(format!("{key}={}", quote(&value)));
+    }
+
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    fs::write(&env_path, contents).expect("Failed to write .env");
+
+    events::status(t!("env_file.created"));
+
+    if !missing.is_empty() {
+        events::status(t!("env_file.missing", keys = missing.join(", ")));
+    }
+}
+
+/// Sets `key` in the project's `.env`, replacing the line that sets it if
+/// there is one. Creates `.env` if there isn't one
+pub fn set(project_dir: &Path, key: &str, value: &str) {
+    let env_path = project_dir.join(".env");
+    let contents = fs::read_to_string(&env_path).unwrap_or_default();
+    let assignment = format!("{key}={}", quote(value));
+
+    let mut replaced = false;
+    let mut lines: Vec<String> = contents
+        .lines()
+        .map(|line| match parse_line(line) {
+            Some((existing, _)) if existing == key && !replaced => {
+                replaced = true;
+                assignment.clone()
+            }
+            _ => line.to_owned(),
+        })
+        .collect();
+
+    if !replaced {
+        lines.push(assignment);
+    }
+
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    fs::write(&env_path, contents).expect("Failed to write .env");
+}
+
+/// `KEY=value`, with an optional `export` in front. Comments and blank lines
+/// give `None`
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (key, value) = line.split_once('=')?;
+
+    Some((key.trim(), value.trim().trim_matches(['"', '\''])))
+}
+
+fn looks_unset(value: &str) -> bool {
+    value.is_empty()
+        || (value.starts_with('<') && value.ends_with('>'))
+        || value.to_lowercase().starts_with("your")
+        || PLACEHOLDERS.contains(&value.to_lowercase().as_str())
+}
+
+fn prompt(key: &str) -> String {
+    Input::<String>::new()
+        .with_prompt(t!("env_file.prompt", key = key))
+        .allow_empty(true)
+        .interact_text()
+        .unwrap_or_else(|e| {
+            eprintln!("{}", theme::error(t!("wizard.read_failed", error = e)));
+            diagnostics::fail();
+        })
+}
+
+/// Quotes values that dotenv would otherwise cut off or misread
+fn quote(value: &str) -> String {
+    if value.contains([' ', '#', '"', '\'']) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_owned()
+    }
+}
@@ -0,0 +1,110 @@
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::time::Instant;
+
+use crate::Config;
+
+/// How progress is reported on stdout
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable messages
+    Text,
+    /// One JSON object per line (NDJSON), for tools driving the quick start
+    Json,
+}
+
+/// A step of the pipeline that's being timed
+pub struct Phase {
+    name: &'static str,
+    start: Instant,
+    /// Logs the phase's timing when it's closed
+    span: tracing::span::EnteredSpan,
+}
+
+/// Starts timing a phase, emitting a `phase_start` event
+pub fn start(name: &'static str) -> Phase {
+    emit(json!({ "event": "phase_start", "phase": name }));
+    crate::telemetry::phase_started(name);
+    crate::timing::phase_started(name);
+    #[cfg(feature = "tui")]
+    crate::tui::phase_started(name);
+
+    Phase {
+        name,
+        start: Instant::now(),
+        span: tracing::info_span!("phase", name).entered(),
+    }
+}
+
+impl Phase {
+    /// Emits a `phase_end` event with the phase's duration. Any fields in
+    /// `data` are included in the event
+    pub fn finish(self, data: Value) {
+        let duration = self.start.elapsed();
+        let duration_ms = duration.as_millis();
+        crate::timing::phase_finished(self.name, duration, &data);
+        #[cfg(feature = "tui")]
+        crate::tui::phase_finished(duration);
+
+        let mut event = json!({
+            "event": "phase_end",
+            "phase": self.name,
+            "duration_ms": duration_ms,
+        });
+
+        if let (Some(event), Value::Object(data)) = (event.as_object_mut(), data) {
+            event.extend(data);
+        }
+
+        emit(event);
+        crate::telemetry::phase_finished(self.name, duration_ms);
+        self.span.exit();
+    }
+}
+
+/// Prints a progress message for humans. In JSON mode it's emitted as a
+/// `message` event instead, to keep stdout machine-readable. Nothing is
+/// printed with `--quiet`, but the message still ends up in the log file.
+/// The TUI shows it next to the running step
+pub fn status(message: impl Display) {
+    tracing::info!(target: crate::logging::STATUS_TARGET, "{message}");
+
+    #[cfg(feature = "tui")]
+    if crate::tui::is_active() {
+        crate::tui::status(&message.to_string());
+        return;
+    }
+
+    if Config::output_format() == OutputFormat::Json {
+        emit(json!({ "event": "message", "message": message.to_string() }));
+    } else if !Config::is_quiet() {
+        println!("{message}");
+    }
+}
+
+/// What the running phase is working on, like which file it's updating.
+/// Too much to print, so it's only logged, and shown in the TUI
+pub fn detail(message: impl Display) {
+    tracing::debug!("{message}");
+
+    #[cfg(feature = "tui")]
+    if crate::tui::is_active() {
+        crate::tui::status(&message.to_string());
+    }
+}
+
+/// How much of a download is done, out of `total` bytes if that's known.
+/// Only shown in the TUI, as the download is usually over in a moment
+#[cfg_attr(not(feature = "tui"), allow(unused_variables))]
+pub fn progress(done: u64, total: Option<u64>) {
+    #[cfg(feature = "tui")]
+    crate::tui::progress(done, total);
+}
+
+/// Writes an event as a single line of JSON. Does nothing unless the output
+/// format is JSON
+pub fn emit(event: Value) {
+    if Config::output_format() == OutputFormat::Json {
+        println!("{event}");
+    }
+}
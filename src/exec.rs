@@ -0,0 +1,667 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cleanup;
+use crate::diagnostics;
+use crate::events::OutputFormat;
+use crate::i18n::t;
+use crate::theme;
+use crate::Config;
+
+/// How often to check if a command has finished or timed out
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How much of a failed command's output is shown. The rest is in the log
+const FAILURE_TAIL_LINES: usize = 20;
+/// How many of the last lines a watchdog is shown when a command stalls
+const STALL_TAIL_LINES: usize = 10;
+
+static COMMAND_TIMEOUT: OnceLock<Option<Duration>> = OnceLock::new();
+static RUNNER: OnceLock<&'static dyn CommandRunner> = OnceLock::new();
+
+/// Runs the commands we start, except for the ones that are handed the
+/// terminal. Can be replaced with `set_runner`, to run without spawning
+/// anything
+pub trait CommandRunner: Send + Sync {
+    /// Runs `command` to completion and captures its output
+    fn output(&self, command: &mut Command) -> Result<Output, Killed>;
+
+    /// Runs `command` to completion, sending each line it prints to `lines`
+    /// as soon as it's printed. `watchdog`, if any, is told when it stops
+    /// printing anything
+    fn stream(
+        &self,
+        command: &mut Command,
+        lines: mpsc::Sender<String>,
+        watchdog: Option<&Watchdog>,
+    ) -> Result<ExitStatus, Killed>;
+}
+
+/// Why a command was killed before it finished
+#[derive(Debug)]
+pub enum Killed {
+    /// It ran for longer than `--command-timeout`
+    TimedOut,
+    /// It stopped printing anything, and its watchdog gave up on it
+    Stalled,
+}
+
+/// Watches a streamed command for output, to tell when it's stalled
+pub struct Watchdog<'a> {
+    /// How long the command may go without printing anything
+    pub stall_after: Duration,
+    /// Called with the last lines the command printed once it's stalled.
+    /// `true` kills it, `false` gives it another `stall_after`
+    pub on_stall: &'a (dyn Fn(&[String]) -> bool + Sync),
+}
+
+/// When a streamed command last printed something, and what
+struct Activity {
+    at: Instant,
+    tail: VecDeque<String>,
+}
+
+/// Spawns the commands, killing them if they run for longer than
+/// `--command-timeout`
+pub struct ProcessRunner;
+
+impl CommandRunner for ProcessRunner {
+    fn output(&self, command: &mut Command) -> Result<Output, Killed> {
+        let mut child = spawn_killable(
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        );
+
+        let stdout_reader = spawn_reader(child.stdout.take().unwrap());
+        let stderr_reader = spawn_reader(child.stderr.take().unwrap());
+
+        let status = wait_with_timeout(&mut child, None)?;
+
+        Ok(Output {
+            status,
+            stdout: stdout_reader.join().expect("Output reader panicked"),
+            stderr: stderr_reader.join().expect("Output reader panicked"),
+        })
+    }
+
+    fn stream(
+        &self,
+        command: &mut Command,
+        lines: mpsc::Sender<String>,
+        watchdog: Option<&Watchdog>,
+    ) -> Result<ExitStatus, Killed> {
+        let mut child = spawn_killable(
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        );
+
+        let activity = Arc::new(Mutex::new(Activity {
+            at: Instant::now(),
+            tail: VecDeque::new(),
+        }));
+        let stdout_reader = spawn_line_reader(
+            child.stdout.take().unwrap(),
+            lines.clone(),
+            activity.clone(),
+        );
+        let stderr_reader =
+            spawn_line_reader(child.stderr.take().unwrap(), lines, activity.clone());
+
+        let status = wait_with_timeout(
+            &mut child,
+            watchdog.map(|watchdog| (watchdog, activity.as_ref())),
+        )?;
+
+        stdout_reader.join().expect("Output reader panicked");
+        stderr_reader.join().expect("Output reader panicked");
+
+        Ok(status)
+    }
+}
+
+/// Makes all commands, except for the interactive ones, go through
+/// `runner`. Has to be called before the first command is run
+#[cfg(feature = "mock")]
+pub fn set_runner(runner: &'static dyn CommandRunner) {
+    if RUNNER.set(runner).is_err() {
+        panic!("The command runner can only be set once");
+    }
+}
+
+fn runner() -> &'static dyn CommandRunner {
+    *RUNNER.get_or_init(|| &ProcessRunner)
+}
+
+/// Sets how long external commands may run before they're killed. `None`
+/// lets them run forever. Commands that are handed the terminal, like the
+/// dev server, are never timed out
+pub fn configure_timeout(timeout: Option<Duration>) {
+    COMMAND_TIMEOUT
+        .set(timeout)
+        .expect("The command timeout can only be configured once");
+}
+
+fn timeout() -> Option<Duration> {
+    *COMMAND_TIMEOUT.get_or_init(|| None)
+}
+
+/// Creates a `Command` for `program`, which is looked up in PATH.
+///
+/// On Windows, package managers and editors are usually `.cmd` or `.ps1`
+/// shims, which `Command::new` doesn't find on its own, as it only looks for
+/// `.exe` files. `.cmd` shims can be run directly once we have their full
+/// path, and `.ps1` shims are run through PowerShell
+pub fn command(program: &str) -> Command {
+    #[cfg(windows)]
+    if let Ok(path) = which::which(program) {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_ascii_lowercase());
+
+        if extension.as_deref() == Some(std::ffi::OsStr::new("ps1")) {
+            let mut command = Command::new("powershell");
+            command
+                .args(["-NoProfile", "-ExecutionPolicy", "Bypass", "-File"])
+                .arg(path);
+            return command;
+        }
+
+        return Command::new(path);
+    }
+
+    Command::new(program)
+}
+
+pub fn exec(args: &[&str]) -> String {
+    exec_with_optional_cwd(args, None).unwrap_or_else(|failed| failed.exit(true))
+}
+
+pub fn exec_in<P: AsRef<Path>>(args: &[&str], cwd: P) -> String {
+    exec_with_optional_cwd(args, Some(cwd.as_ref())).unwrap_or_else(|failed| failed.exit(true))
+}
+
+/// Formats a command for messages, quoting any arguments that contain
+/// whitespace
+pub fn display_command(args: &[&str]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.contains(char::is_whitespace) {
+                format!("\"{}\"", arg.replace('"', "\\\""))
+            } else {
+                arg.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Internal function to execute a command with an optional current working
+/// directory
+/// Prefer `exec` or `exec_in` instead of this function for actual usage in the
+/// code as they provide a more ergonomic interface
+///
+/// `args` is passed to the command as-is, without going through a shell, so
+/// arguments with spaces or quotes in them don't need any escaping
+fn exec_with_optional_cwd(
+    args: &[&str],
+    cwd_option: Option<&Path>,
+) -> Result<String, CommandFailed> {
+    let (cmd, cmd_args) = args.split_first().expect("No command provided");
+    let _span = tracing::trace_span!("exec", command = %display_command(args)).entered();
+
+    let mut command = command(cmd);
+    command.args(cmd_args);
+
+    if let Some(cwd) = cwd_option {
+        command.current_dir(cwd);
+    }
+
+    let output = output_with_timeout(&mut command, &display_command(args));
+
+    if !output.status.success() {
+        let failed = CommandFailed::new(
+            args,
+            cwd_option,
+            output.status,
+            [output.stdout, output.stderr]
+                .map(|output| String::from_utf8_lossy(&output).into_owned())
+                .join(""),
+        );
+        tracing::debug!("{failed}");
+
+        return Err(failed);
+    }
+
+    let output = String::from_utf8(output.stdout).expect("Failed to parse output");
+
+    tracing::debug!("`{}` output:\n{output}", display_command(args));
+
+    Ok(output)
+}
+
+/// Runs `command` to completion and captures its output, like
+/// `Command::output`, but kills it if it runs for longer than
+/// `--command-timeout`. `description` is used in the error message
+pub fn output_with_timeout(command: &mut Command, description: &str) -> Output {
+    runner()
+        .output(command)
+        .unwrap_or_else(|_| time_out(description))
+}
+
+/// Like `exec_in`, but for long running commands. Output is streamed as it's
+/// produced instead of only being available once the command finishes.
+///
+/// With `--show-output` (or `--verbose`) every line is printed as-is.
+/// Otherwise a single status line with a spinner and the latest output line
+/// is kept updated, unless `--quiet` is given. The output is always captured,
+/// logged, and printed in full if the command fails
+pub fn exec_streaming_in<P: AsRef<Path>>(args: &[&str], cwd: P) -> String {
+    exec_streaming_in_with_env(args, cwd, &[])
+}
+
+/// Like `exec_streaming_in`, with `env` added to the command's environment
+pub fn exec_streaming_in_with_env<P: AsRef<Path>>(
+    args: &[&str],
+    cwd: P,
+    env: &[(String, String)],
+) -> String {
+    try_exec_streaming_in_with_env(args, cwd, env)
+        .unwrap_or_else(|failed| failed.exit(!Config::shows_output()))
+}
+
+/// Like `exec_streaming_in_with_env`, with `watchdog` watching for the
+/// command to stall. `None` if the watchdog had it killed
+pub fn exec_streaming_watched<P: AsRef<Path>>(
+    args: &[&str],
+    cwd: P,
+    env: &[(String, String)],
+    watchdog: &Watchdog,
+) -> Option<String> {
+    stream_in(args, cwd.as_ref(), env, Some(watchdog))
+        .map(|result| result.unwrap_or_else(|failed| failed.exit(!Config::shows_output())))
+}
+
+/// A command that exited with an error, with everything needed to tell why
+#[derive(Debug)]
+pub struct CommandFailed {
+    /// The whole command line
+    pub args: Vec<String>,
+    /// Where it ran. `None` for the current dir
+    pub cwd: Option<PathBuf>,
+    pub status: ExitStatus,
+    /// Everything it printed. Streamed commands have stdout and stderr
+    /// interleaved as they came in, the others have stderr after stdout
+    pub output: String,
+}
+
+impl CommandFailed {
+    fn new(args: &[&str], cwd: Option<&Path>, status: ExitStatus, output: String) -> Self {
+        CommandFailed {
+            args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+            cwd: cwd.map(|cwd| std::path::absolute(cwd).unwrap_or(cwd.to_path_buf())),
+            status,
+            output,
+        }
+    }
+
+    /// Like "`yarn install` exited with code 1"
+    pub fn summary(&self) -> String {
+        let command = display_command(&self.args.iter().map(String::as_str).collect::<Vec<_>>());
+
+        match self.status.code() {
+            Some(code) => t!("command_failed.exit_code", command = command, code = code),
+            None => t!("command_failed.killed", command = command),
+        }
+    }
+
+    /// Where the command ran, the end of its output unless `with_output` is
+    /// false because it's already been shown, and what might have caused
+    /// the failure, one per line
+    pub fn details(&self, with_output: bool) -> String {
+        let mut lines = Vec::new();
+
+        if let Some(cwd) = &self.cwd {
+            lines.push(t!("command_failed.cwd", dir = cwd.to_string_lossy()));
+        }
+
+        let output: Vec<&str> = self
+            .output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        if with_output && !output.is_empty() {
+            let tail = &output[output.len().saturating_sub(FAILURE_TAIL_LINES)..];
+
+            lines.push(if tail.len() < output.len() {
+                t!("command_failed.tail", count = tail.len())
+            } else {
+                t!("command_failed.output")
+            });
+            lines.extend(tail.iter().map(|line| format!("  {}", theme::dim(line))));
+        }
+
+        if let Some(hint) = self.hint() {
+            lines.push(hint);
+        }
+
+        lines.join("\n")
+    }
+
+    /// What commonly causes the errors in the output
+    fn hint(&self) -> Option<String> {
+        let output = self.output.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|needle| output.contains(needle));
+
+        let hint = if has(&["enospc", "no space left on device"]) {
+            t!("command_failed.hint.disk_full")
+        } else if has(&["eacces", "eperm", "permission denied"]) {
+            t!("command_failed.hint.permissions")
+        } else if has(&[
+            "unable to get local issuer certificate",
+            "self signed certificate",
+            "self-signed certificate",
+            "unable_to_verify_leaf_signature",
+        ]) {
+            t!("command_failed.hint.certificate")
+        } else if has(&[
+            "etimedout",
+            "econnreset",
+            "econnrefused",
+            "enotfound",
+            "eai_again",
+            "getaddrinfo",
+            "socket hang up",
+            "could not resolve host",
+        ]) {
+            t!("command_failed.hint.network")
+        } else if has(&["yn0028", "lockfile would have been modified"]) {
+            t!("command_failed.hint.immutable")
+        } else if has(&["please tell me who you are", "author identity unknown"]) {
+            t!("command_failed.hint.git_identity")
+        } else if has(&["gpg failed to sign"]) {
+            t!("command_failed.hint.gpg")
+        } else if has(&["gyp err", "node-gyp"]) {
+            t!("command_failed.hint.build_tools")
+        } else if has(&["unsupported engine", "is incompatible with this module"]) {
+            t!("command_failed.hint.engine")
+        } else {
+            return None;
+        };
+
+        Some(hint)
+    }
+
+    /// Explains the failure and exits
+    fn exit(&self, with_output: bool) -> ! {
+        eprintln!("{}", theme::error(self.summary()));
+        eprintln!("{}", self.details(with_output));
+        diagnostics::fail();
+    }
+}
+
+impl fmt::Display for CommandFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n{}", self.summary(), self.details(true))
+    }
+}
+
+impl std::error::Error for CommandFailed {}
+
+/// Like `exec_streaming_in_with_env`, but a command that fails is returned
+/// as an error instead of exiting, for callers that explain the failure
+/// themselves. Timing out still exits
+pub fn try_exec_streaming_in_with_env<P: AsRef<Path>>(
+    args: &[&str],
+    cwd: P,
+    env: &[(String, String)],
+) -> Result<String, CommandFailed> {
+    stream_in(args, cwd.as_ref(), env, None).expect("Killed without a watchdog")
+}
+
+/// Streams the command's output, as described for `exec_streaming_in`.
+/// `None` if `watchdog` had it killed
+fn stream_in(
+    args: &[&str],
+    cwd: &Path,
+    env: &[(String, String)],
+    watchdog: Option<&Watchdog>,
+) -> Option<Result<String, CommandFailed>> {
+    let (cmd, cmd_args) = args.split_first().expect("No command provided");
+    let _span = tracing::debug_span!("exec", command = %display_command(args)).entered();
+
+    let mut command = command(cmd);
+    command
+        .args(cmd_args)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .current_dir(cwd);
+
+    let (tx, rx) = mpsc::channel();
+
+    let (result, output) = thread::scope(|scope| {
+        let command = &mut command;
+        let run = scope.spawn(move || runner().stream(command, tx, watchdog));
+        let output = show_lines(rx);
+
+        (run.join().expect("Command runner panicked"), output)
+    });
+
+    tracing::debug!("`{}` output:\n{output}", display_command(args));
+
+    let status = match result {
+        Ok(status) => status,
+        Err(Killed::Stalled) => return None,
+        Err(Killed::TimedOut) => {
+            eprintln!("{output}");
+            time_out(&display_command(args));
+        }
+    };
+
+    if status.success() {
+        Some(Ok(output))
+    } else {
+        Some(Err(CommandFailed::new(args, Some(cwd), status, output)))
+    }
+}
+
+/// Shows the lines a command prints as they come in, as described for
+/// `exec_streaming_in`. Returns all of them once the command is done
+fn show_lines(lines: mpsc::Receiver<String>) -> String {
+    #[cfg(feature = "tui")]
+    if crate::tui::is_active() {
+        return crate::tui::show_lines(lines);
+    }
+
+    let show_output = Config::shows_output();
+    let show_status = shows_status_line();
+    let json_output = Config::output_format() == OutputFormat::Json;
+    let mut output = String::new();
+    let mut spinner = SPINNER_FRAMES.iter().cycle();
+
+    // The channel closes once the command is done, and all its output has
+    // been read
+    for line in lines {
+        if show_output && json_output {
+            eprintln!("{}", theme::dim(&line).for_stderr());
+        } else if show_output {
+            println!("{}", theme::dim(&line));
+        } else if show_status {
+            let status: String = line.chars().take(STATUS_LINE_WIDTH).collect();
+            print!(
+                "\r\x1b[2K{} {}",
+                spinner.next().unwrap(),
+                theme::dim(status)
+            );
+            std::io::stdout().flush().ok();
+        }
+
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    if show_status {
+        clear_status_line();
+    }
+
+    output
+}
+
+/// Whether a streamed command's latest line is shown in a status line,
+/// rather than every line. Never with JSON output, to keep stdout clean for
+/// the events
+fn shows_status_line() -> bool {
+    !Config::shows_output() && Config::output_format() != OutputFormat::Json && !Config::is_quiet()
+}
+
+/// Clears the status line of a streamed command, so what's printed next
+/// starts on a line of its own
+pub fn clear_status_line() {
+    #[cfg(feature = "tui")]
+    if crate::tui::is_active() {
+        return;
+    }
+
+    if shows_status_line() {
+        print!("\r\x1b[2K");
+        std::io::stdout().flush().ok();
+    }
+}
+
+/// Runs a command with the terminal handed over to it, so its output goes
+/// straight to the user and it can read input
+pub fn exec_interactive_in<P: AsRef<Path>>(args: &[&str], cwd: P) -> ExitStatus {
+    let (cmd, cmd_args) = args.split_first().expect("No command provided");
+
+    command(cmd)
+        .args(cmd_args)
+        .current_dir(cwd.as_ref())
+        .status()
+        .expect("Failed to execute command")
+}
+
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const STATUS_LINE_WIDTH: usize = 72;
+
+/// Spawns the command in its own process group, so it and everything it
+/// starts can be killed together. That also means it doesn't get the
+/// terminal's Ctrl-C, so the cleanup handler passes it on
+fn spawn_killable(command: &mut Command) -> Child {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+
+    let child = command.spawn().expect("Failed to execute command");
+    cleanup::register_child(child.id());
+
+    child
+}
+
+/// Waits for the command to exit, killing it if it runs for longer than
+/// `--command-timeout`, or if it stalls and the watchdog says so
+fn wait_with_timeout(
+    child: &mut Child,
+    watch: Option<(&Watchdog, &Mutex<Activity>)>,
+) -> Result<ExitStatus, Killed> {
+    let deadline = timeout().map(|timeout| Instant::now() + timeout);
+
+    let status = loop {
+        if let Some(status) = child.try_wait().expect("Failed to wait for command") {
+            break status;
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            kill(child);
+            return Err(Killed::TimedOut);
+        }
+
+        if let Some((watchdog, activity)) = watch {
+            let stalled = {
+                let activity = activity.lock().unwrap();
+                (activity.at.elapsed() >= watchdog.stall_after)
+                    .then(|| activity.tail.iter().cloned().collect::<Vec<_>>())
+            };
+
+            if let Some(tail) = stalled {
+                tracing::debug!("No output for {:?}", watchdog.stall_after);
+
+                if (watchdog.on_stall)(&tail) {
+                    kill(child);
+                    return Err(Killed::Stalled);
+                }
+
+                activity.lock().unwrap().at = Instant::now();
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    cleanup::unregister_child(child.id());
+
+    Ok(status)
+}
+
+fn kill(child: &mut Child) {
+    cleanup::kill_child(child.id());
+    child.wait().ok();
+    cleanup::unregister_child(child.id());
+}
+
+fn time_out(description: &str) -> ! {
+    eprintln!(
+        "{}",
+        theme::error(t!(
+            "command_failed.timed_out",
+            command = description,
+            timeout = format!("{:?}", timeout().unwrap_or_default())
+        ))
+    );
+    eprintln!("{}", t!("command_failed.timed_out_hint"));
+    diagnostics::fail();
+}
+
+fn spawn_reader<R: Read + Send + 'static>(mut reader: R) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).ok();
+        buf
+    })
+}
+
+fn spawn_line_reader<R: Read + Send + 'static>(
+    reader: R,
+    tx: mpsc::Sender<String>,
+    activity: Arc<Mutex<Activity>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            {
+                let mut activity = activity.lock().unwrap();
+                activity.at = Instant::now();
+                if !line.trim().is_empty() {
+                    activity.tail.push_back(line.clone());
+                    if activity.tail.len() > STALL_TAIL_LINES {
+                        activity.tail.pop_front();
+                    }
+                }
+            }
+
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    })
+}
@@ -0,0 +1,368 @@
+use serde_json::json;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::i18n::t;
+use crate::source::Source;
+use crate::{archive, cleanup, diagnostics, events, fs_util, git, theme};
+#[cfg(feature = "async")]
+use crate::{archive_stream, http};
+
+/// What the names of our temp dirs start with
+pub const TEMP_DIR_PREFIX: &str = "rwjs-rsc-quickstart-";
+
+/// How to get the template out of the Redwood repo
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Via {
+    /// Use git if it's installed, and fall back to the zip archive
+    Auto,
+    /// Shallow, sparse clone of only the template
+    Git,
+    /// Download the repo archive, in --format, and extract the template
+    /// from it
+    Zip,
+}
+
+/// Picks the way to get the template. `auto` uses git if it's available,
+/// unless a `--sha256` digest is given, as that's only for the archive
+pub fn resolve_via(source: &Source, via: Via, sha256: Option<&str>) -> Via {
+    if let (Source::Local(_), Some(_)) = (source, sha256) {
+        eprintln!("{}", theme::error(t!("fetch.sha256_local")));
+        diagnostics::fail();
+    }
+
+    match via {
+        Via::Git if sha256.is_some() => {
+            eprintln!("{}", theme::error(t!("fetch.sha256_git")));
+            eprintln!("{}", t!("fetch.sha256_git_hint"));
+            diagnostics::fail();
+        }
+        // There's nothing to clone
+        _ if matches!(source, Source::Archive(_)) => Via::Zip,
+        Via::Auto if sha256.is_some() => Via::Zip,
+        Via::Auto if which::which("git").is_ok() => Via::Git,
+        Via::Auto => Via::Zip,
+        via => via,
+    }
+}
+
+/// How to download the archive, when the template isn't cloned
+#[derive(Debug)]
+pub struct ArchiveOptions {
+    pub format: archive::Format,
+    /// The expected SHA-256 digest of the archive
+    pub sha256: Option<String>,
+    /// Extract the whole repo, instead of only the template
+    pub extract_all: bool,
+}
+
+/// A download that's kept around to create more projects from
+struct SharedDownload {
+    source: Source,
+    target_dir: PathBuf,
+    commit: Option<String>,
+}
+
+/// `None` unless downloads are shared
+static SHARED_DOWNLOADS: Mutex<Option<Vec<SharedDownload>>> = Mutex::new(None);
+
+/// Keeps what's downloaded from GitHub around for the next project, instead
+/// of downloading it again. Used when creating several projects in one go
+pub fn share_downloads() {
+    *SHARED_DOWNLOADS.lock().unwrap() = Some(Vec::new());
+}
+
+/// Removes the downloads kept by `share_downloads`
+pub fn remove_shared_downloads() {
+    let downloads = SHARED_DOWNLOADS.lock().unwrap().take();
+
+    for download in downloads.into_iter().flatten() {
+        cleanup::remove_temp_dir(&download.target_dir);
+    }
+}
+
+/// Gets `__fixtures__/<template>` from `source` into `installation_dir`.
+/// If it already exists, like when it's `.`, the template is put in it
+/// rather than in its place, keeping anything that's already there, like a
+/// README or `.git`.
+///
+/// With `fallback`, a failed git clone is retried by downloading the archive.
+/// Local sources are copied, so uncommitted changes are included.
+///
+/// Returns the commit the template was taken from, if it's known
+pub fn fetch_template(
+    source: &Source,
+    via: Via,
+    fallback: bool,
+    template: &str,
+    archive: &ArchiveOptions,
+    installation_dir: &Path,
+) -> Option<String> {
+    let fixture_path = format!("__fixtures__/{template}");
+    let share =
+        matches!(source, Source::GitHub { .. }) && SHARED_DOWNLOADS.lock().unwrap().is_some();
+
+    let (target_dir, commit) = shared_download(source, &fixture_path).unwrap_or_else(|| {
+        let target_dir = fs_util::long_path(&get_tempdir());
+        cleanup::register_temp_dir(&target_dir);
+
+        // A shared download has to have every template in it
+        let only = (!archive.extract_all && !share).then(|| Path::new(&fixture_path));
+        let commit = fetch(
+            source,
+            via,
+            fallback,
+            &fixture_path,
+            archive,
+            &target_dir,
+            only,
+        );
+
+        if share {
+            if let Some(downloads) = SHARED_DOWNLOADS.lock().unwrap().as_mut() {
+                downloads.push(SharedDownload {
+                    source: source.clone(),
+                    target_dir: target_dir.clone(),
+                    commit: commit.clone(),
+                });
+            }
+        }
+
+        (target_dir, commit)
+    });
+
+    let phase = events::start("extract");
+    cleanup::register_partial_project(installation_dir);
+
+    let fetched = target_dir.join(&fixture_path);
+    // Only the template is extracted, so a template that doesn't exist
+    // leaves nothing behind rather than failing the extraction
+    if !fetched.is_dir() {
+        eprintln!(
+            "{}",
+            theme::error(t!(
+                "errors.template_not_found",
+                template = template,
+                source = source
+            ))
+        );
+        eprintln!("{}", t!("errors.template_not_found_hint"));
+        diagnostics::fail();
+    }
+
+    let in_place = installation_dir.exists();
+    let installation_dir = fs_util::long_path(installation_dir);
+
+    let kept = match (in_place, share) {
+        (true, true) => fs_util::copy_dir_contents(&fetched, &installation_dir),
+        (true, false) => fs_util::move_dir_contents(&fetched, &installation_dir),
+        (false, true) => fs_util::copy_dir_all(&fetched, &installation_dir).map(|()| Vec::new()),
+        (false, false) => fs_util::move_dir(&fetched, &installation_dir).map(|()| Vec::new()),
+    }
+    .expect("Failed to move project");
+
+    if !share {
+        cleanup::remove_temp_dir(&target_dir);
+    }
+
+    for path in kept {
+        events::status(t!(
+            "fetch.kept",
+            file = path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+    }
+
+    phase.finish(json!({ "template": template }));
+
+    commit
+}
+
+/// A shared download of `source` that has `fixture_path` in it. Sparse
+/// clones only have the template they were made for
+fn shared_download(source: &Source, fixture_path: &str) -> Option<(PathBuf, Option<String>)> {
+    let downloads = SHARED_DOWNLOADS.lock().unwrap();
+
+    downloads
+        .iter()
+        .flatten()
+        .find(|download| {
+            download.source == *source && download.target_dir.join(fixture_path).is_dir()
+        })
+        .map(|download| {
+            tracing::debug!(
+                "Reusing the download in {}",
+                download.target_dir.to_string_lossy()
+            );
+            (download.target_dir.clone(), download.commit.clone())
+        })
+}
+
+/// Gets the contents of `source` into `target_dir`, so that the template is
+/// in `target_dir/fixture_path`. Archives are only extracted from `only`, if
+/// it's given. Returns the commit, if it's known
+fn fetch(
+    source: &Source,
+    via: Via,
+    fallback: bool,
+    fixture_path: &str,
+    archive: &ArchiveOptions,
+    target_dir: &Path,
+    only: Option<&Path>,
+) -> Option<String> {
+    match source {
+        Source::GitHub { repo, branch } => {
+            let repo_url = source.repo_url().unwrap();
+            let archive_url = source.archive_url(archive.format).unwrap();
+            tracing::debug!("Fetching {fixture_path} from {repo}@{branch}");
+
+            let cloned =
+                via == Via::Git && clone(&repo_url, branch, fixture_path, target_dir, fallback);
+
+            if cloned {
+                git::head_commit(target_dir)
+            } else {
+                download(&archive_url, archive, target_dir, only)
+            }
+        }
+        Source::Local(path) => {
+            copy_local(path, fixture_path, target_dir);
+            git::head_commit(path)
+        }
+        Source::Archive(location) => match source.archive_url(archive.format) {
+            Some(url) => download(&url, archive, target_dir, only),
+            None => extract_file(&fs_util::expand_tilde(location), archive, target_dir, only),
+        },
+    }
+}
+
+/// Sparse clones `fixture_path` from `repo_url`. Returns false if it failed
+/// and we should fall back to downloading the archive
+fn clone(
+    repo_url: &str,
+    branch: &str,
+    fixture_path: &str,
+    target_dir: &Path,
+    fallback: bool,
+) -> bool {
+    let phase = events::start("download");
+    match git::sparse_clone(repo_url, branch, fixture_path, target_dir) {
+        Ok(()) => {
+            phase.finish(json!({ "via": "git", "url": repo_url }));
+            true
+        }
+        Err(e) if fallback => {
+            eprintln!(
+                "{}",
+                theme::warning(t!("fetch.clone_failed", url = repo_url, error = e))
+            );
+            eprintln!("{}", t!("fetch.clone_fallback"));
+
+            // Start over with an empty dir for the archive
+            fs::remove_dir_all(target_dir).expect("Failed to clear temp dir");
+            fs::create_dir(target_dir).expect("Failed to create temp dir");
+            false
+        }
+        Err(e) => {
+            eprintln!(
+                "{}",
+                theme::error(t!("fetch.clone_failed", url = repo_url, error = e))
+            );
+            diagnostics::fail();
+        }
+    }
+}
+
+/// Downloads and extracts the archive. Returns the commit it was made from.
+///
+/// With the `async` feature the archive is extracted while it downloads,
+/// unless requests go through a fetcher that can't stream
+fn download(
+    archive_url: &str,
+    options: &ArchiveOptions,
+    target_dir: &Path,
+    only: Option<&Path>,
+) -> Option<String> {
+    let phase = events::start("download");
+    let sha256 = options.sha256.as_deref();
+
+    #[cfg(feature = "async")]
+    if http::fetcher().streams_downloads() {
+        let commit = archive_stream::download_and_extract(
+            archive_url,
+            sha256,
+            options.format,
+            target_dir,
+            only,
+        );
+        phase.finish(json!({ "via": "zip", "url": archive_url, "streamed": true }));
+
+        return commit;
+    }
+
+    let (archive, format) = archive::download(archive_url, sha256, options.format);
+    phase.finish(json!({
+        "via": "zip",
+        "url": archive_url,
+        "format": format.extension(),
+        "bytes": archive.len(),
+    }));
+
+    tracing::debug!("Extracting into {}", target_dir.to_string_lossy());
+
+    archive::extract(format, Cursor::new(&archive), target_dir, only)
+        .expect("Failed to extract archive")
+}
+
+/// Extracts an archive that's already been downloaded. Returns the commit it
+/// was made from
+fn extract_file(
+    path: &Path,
+    options: &ArchiveOptions,
+    target_dir: &Path,
+    only: Option<&Path>,
+) -> Option<String> {
+    let phase = events::start("download");
+    let (archive, format) = archive::read_file(path, options.sha256.as_deref(), options.format);
+    phase.finish(json!({
+        "via": "file",
+        "path": path.to_string_lossy(),
+        "format": format.extension(),
+        "bytes": archive.len(),
+    }));
+
+    tracing::debug!("Extracting into {}", target_dir.to_string_lossy());
+
+    archive::extract(format, Cursor::new(&archive), target_dir, only)
+        .expect("Failed to extract archive")
+}
+
+/// Copies the fixture out of a local checkout, into the temp dir. That keeps
+/// the checkout untouched if something goes wrong later on
+fn copy_local(source_dir: &Path, fixture_path: &str, target_dir: &Path) {
+    let from = source_dir.join(fixture_path);
+
+    if !from.is_dir() {
+        eprintln!(
+            "{}",
+            theme::error(t!("fetch.not_found", path = from.to_string_lossy()))
+        );
+        diagnostics::fail();
+    }
+
+    let phase = events::start("download");
+    let to = target_dir.join(fixture_path);
+    fs::create_dir_all(to.parent().unwrap()).expect("Failed to create temp dir");
+    fs_util::copy_dir_all(&fs_util::long_path(&from), &to).expect("Failed to copy the template");
+    phase.finish(json!({ "via": "local", "path": from.to_string_lossy() }));
+}
+
+fn get_tempdir() -> PathBuf {
+    tempfile::Builder::new()
+        .prefix(TEMP_DIR_PREFIX)
+        .rand_bytes(12)
+        .tempdir()
+        .unwrap()
+        .into_path()
+}
@@ -0,0 +1,188 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Expands a leading `~` to the user's home directory. The shell does this
+/// for unquoted arguments, but not for paths from the wizard, the config file
+/// or env vars
+pub fn expand_tilde(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some("") => "",
+        Some(rest) if rest.starts_with(['/', std::path::MAIN_SEPARATOR]) => &rest[1..],
+        _ => return PathBuf::from(path),
+    };
+
+    match dirs::home_dir() {
+        Some(home) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Where we cache things between runs, like packuments and unfinished
+/// downloads
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("rw-rsc-quickstart"))
+}
+
+/// Windows limits paths to 260 characters unless they're in the verbatim
+/// `\\?\` form, and the Redwood repo has some deeply nested files. Elsewhere
+/// the path is returned as it is
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    if let Ok(absolute) = std::path::absolute(path) {
+        let absolute_str = absolute.to_string_lossy();
+
+        if absolute_str.starts_with(r"\\?\") {
+            return absolute;
+        }
+
+        if let Some(unc) = absolute_str.strip_prefix(r"\\") {
+            return PathBuf::from(format!(r"\\?\UNC\{unc}"));
+        }
+
+        return PathBuf::from(format!(r"\\?\{absolute_str}"));
+    }
+
+    path.to_path_buf()
+}
+
+/// Moves a directory, falling back to copying and then deleting it if it
+/// can't just be renamed because `from` and `to` are on different
+/// filesystems. That's common when `/tmp` is a tmpfs
+pub fn move_dir(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            tracing::debug!(
+                "Can't rename {} across filesystems. Copying instead",
+                from.to_string_lossy()
+            );
+
+            copy_dir_all(from, to)?;
+            fs::remove_dir_all(from)
+        }
+        result => result,
+    }
+}
+
+/// Moves everything in `from` into `to`, which already exists. Anything
+/// that's already in `to` is kept, instead of what's in `from`. Returns
+/// what was kept
+pub fn move_dir_contents(from: &Path, to: &Path) -> io::Result<Vec<PathBuf>> {
+    merge_dir(from, to, false)
+}
+
+/// Like `move_dir_contents`, but leaves `from` as it is
+pub fn copy_dir_contents(from: &Path, to: &Path) -> io::Result<Vec<PathBuf>> {
+    merge_dir(from, to, true)
+}
+
+/// The names of everything in `dir`. `None` if it can't be read, like when
+/// it doesn't exist
+pub fn entry_names(dir: &Path) -> Option<Vec<OsString>> {
+    fs::read_dir(dir)
+        .ok()
+        .map(|entries| entries.flatten().map(|entry| entry.file_name()).collect())
+}
+
+/// Removes everything in `dir` except the entries named in `keep`. Carries
+/// on past anything that can't be removed, and returns the first error
+pub fn remove_dir_contents_except(dir: &Path, keep: &[OsString]) -> io::Result<()> {
+    let mut result = Ok(());
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if keep.contains(&entry.file_name()) {
+            continue;
+        }
+
+        let removed = match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(entry.path()),
+            Ok(_) => fs::remove_file(entry.path()),
+            Err(e) => Err(e),
+        };
+
+        if result.is_ok() {
+            result = removed;
+        }
+    }
+
+    result
+}
+
+fn merge_dir(from: &Path, to: &Path, copy: bool) -> io::Result<Vec<PathBuf>> {
+    let mut kept = Vec::new();
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = to.join(entry.file_name());
+
+        if target.symlink_metadata().is_ok() {
+            kept.push(target);
+        } else if file_type.is_dir() && copy {
+            copy_dir_all(&entry.path(), &target)?;
+        } else if file_type.is_dir() {
+            move_dir(&entry.path(), &target)?;
+        } else if copy {
+            copy_file(&entry.path(), &target, file_type)?;
+        } else {
+            match fs::rename(entry.path(), &target) {
+                Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                    copy_file(&entry.path(), &target, file_type)?;
+                    fs::remove_file(entry.path())?;
+                }
+                result => result?,
+            }
+        }
+    }
+
+    Ok(kept)
+}
+
+fn copy_file(from: &Path, to: &Path, file_type: fs::FileType) -> io::Result<()> {
+    if file_type.is_symlink() {
+        copy_symlink(from, to)
+    } else {
+        // Copies the permissions as well
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Recursively copies a directory, preserving permissions and symlinks
+pub fn copy_dir_all(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = to.join(entry.file_name());
+
+        if file_type.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            copy_file(&entry.path(), &target, file_type)?;
+        }
+    }
+
+    fs::set_permissions(to, fs::metadata(from)?.permissions())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let link_target = fs::read_link(from)?;
+
+    // Relative link targets are relative to the link's directory
+    let resolved = from.parent().unwrap_or(Path::new("")).join(&link_target);
+
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(link_target, to)
+    } else {
+        std::os::windows::fs::symlink_file(link_target, to)
+    }
+}
@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+
+use crate::exec;
+use crate::i18n::t;
+
+/// How to initialize the project's repository
+#[derive(Debug)]
+pub struct InitOptions<'a> {
+    pub branch: &'a str,
+    /// `Name <email>`. Used as both author and committer of the initial
+    /// commit, so it works even if git has no identity configured
+    pub author: Option<&'a str>,
+    pub commit_message: &'a str,
+    /// Signs the initial commit. An empty string signs with the default key
+    pub gpg_sign: Option<&'a str>,
+    /// Skips the pre-commit and commit-msg hooks
+    pub no_verify: bool,
+    /// Sets up the project's git hooks, once the repository exists but
+    /// before anything is committed
+    pub hooks_setup: Option<Vec<String>>,
+    /// Files in the project that are left out of the initial commit, and
+    /// stay untracked
+    pub exclude: Vec<&'a str>,
+    /// The project dir is already a repository, like when it's scaffolded
+    /// into `.`. It's committed to as it is, on its current branch
+    pub existing_repo: bool,
+}
+
+/// Finds the git repository the project would be nested in, if any. The
+/// project dir doesn't have to exist yet
+pub fn find_enclosing_repo(project_dir: &Path) -> Option<PathBuf> {
+    let project_dir = std::path::absolute(project_dir).ok()?;
+
+    project_dir
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Shallow, sparse clone of `branch` that only checks out `path`. Blobs
+/// outside of `path` aren't downloaded at all
+pub fn sparse_clone(
+    repo_url: &str,
+    branch: &str,
+    path: &str,
+    target_dir: &Path,
+) -> Result<(), String> {
+    let target = target_dir.to_string_lossy();
+
+    run_git(&[
+        "clone",
+        "--depth",
+        "1",
+        "--filter=blob:none",
+        "--sparse",
+        // Only does something on Windows, where paths are limited to 260
+        // characters by default
+        "--config",
+        "core.longpaths=true",
+        "--branch",
+        branch,
+        repo_url,
+        &target,
+    ])?;
+    run_git(&["-C", &target, "sparse-checkout", "set", path])?;
+
+    if !target_dir.join(path).is_dir() {
+        return Err(t!("fetch.not_in_repo", path = path, url = repo_url));
+    }
+
+    Ok(())
+}
+
+/// The commit checked out in `repo_dir`, if it's a git repository
+pub fn head_commit(repo_dir: &Path) -> Option<String> {
+    which::which("git").ok()?;
+
+    run_git(&["-C", &repo_dir.to_string_lossy(), "rev-parse", "HEAD"]).ok()
+}
+
+/// Runs git, returning what it printed to stdout. If it fails, what it
+/// printed to stderr is returned instead
+fn run_git(args: &[&str]) -> Result<String, String> {
+    let description = format!("git {}", args.join(" "));
+    let _span = tracing::debug_span!("git", args = %args.join(" ")).entered();
+
+    let output = exec::output_with_timeout(exec::command("git").args(args), &description);
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_owned())
+    }
+}
+
+/// The commands that create the repository with an initial commit of
+/// everything in it. An existing repository just gets the commit
+pub fn init_commands(options: &InitOptions) -> Vec<Vec<String>> {
+    let mut commit = vec!["git".to_owned()];
+
+    if let Some((name, email)) = options.author.map(parse_author) {
+        commit.extend([
+            "-c".to_owned(),
+            format!("user.name={name}"),
+            "-c".to_owned(),
+            format!("user.email={email}"),
+        ]);
+    }
+
+    commit.extend([
+        "commit".to_owned(),
+        "-m".to_owned(),
+        options.commit_message.to_owned(),
+    ]);
+
+    match options.gpg_sign {
+        Some("") => commit.push("--gpg-sign".to_owned()),
+        Some(key) => commit.push(format!("--gpg-sign={key}")),
+        None => {}
+    }
+
+    if options.no_verify {
+        commit.push("--no-verify".to_owned());
+    }
+
+    let mut commands = Vec::new();
+
+    if !options.existing_repo {
+        commands.push(to_strings(&["git", "init", "."]));
+        // Works with all git versions, unlike `git init --initial-branch`
+        commands.push(to_strings(&[
+            "git",
+            "symbolic-ref",
+            "HEAD",
+            &format!("refs/heads/{}", options.branch),
+        ]));
+    }
+
+    commands.extend(options.hooks_setup.clone());
+    let mut add = to_strings(&["git", "add", "--", "."]);
+    add.extend(
+        options
+            .exclude
+            .iter()
+            .map(|path| format!(":(exclude){path}")),
+    );
+    commands.push(add);
+    commands.push(commit);
+
+    commands
+}
+
+/// Splits `Name <email>` into its parts. Without an `<email>` part the whole
+/// string is used as the name
+fn parse_author(author: &str) -> (&str, &str) {
+    match author.split_once('<') {
+        Some((name, email)) => (name.trim(), email.trim().trim_end_matches('>')),
+        None => (author.trim(), ""),
+    }
+}
+
+fn to_strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
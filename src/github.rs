@@ -0,0 +1,202 @@
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::http::{self, BodyReader, FetchError, HttpResponse};
+use crate::i18n::t;
+
+static TOKEN: OnceLock<Option<Token>> = OnceLock::new();
+
+/// A GitHub access token. Kept out of debug output, so it doesn't end up in
+/// logs
+#[derive(Clone)]
+pub struct Token(String);
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Token(***)")
+    }
+}
+
+impl FromStr for Token {
+    type Err = Infallible;
+
+    fn from_str(token: &str) -> Result<Self, Self::Err> {
+        Ok(Token(token.to_owned()))
+    }
+}
+
+/// Sets the token to authenticate GitHub requests with. Falls back to
+/// `GITHUB_TOKEN` and `GH_TOKEN`, which CI providers and the `gh` CLI use.
+///
+/// Anonymous requests are limited to 60 API calls an hour per IP, which
+/// shared CI runners easily hit
+pub fn configure(token: Option<Token>) {
+    let token = token.or_else(|| {
+        ["GITHUB_TOKEN", "GH_TOKEN"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|token| !token.is_empty())
+            .map(Token)
+    });
+
+    if token.is_some() {
+        tracing::debug!("Authenticating with GitHub using a token");
+    }
+
+    TOKEN
+        .set(token)
+        .expect("The GitHub token can only be configured once");
+}
+
+/// GETs a url on github.com, its API, or one of the hosts it redirects
+/// downloads to, like codeload.github.com.
+///
+/// The token is only sent to GitHub. Redirects are followed by the http
+/// client, which drops the token if they lead to another host. Running into
+/// the rate limit is reported with the time it resets
+pub fn get(url: &str) -> Result<HttpResponse, FetchError> {
+    let response = http::fetcher().get(url, with_token(url, HeaderMap::new())?)?;
+
+    if let Some(error) = rate_limit_error(response.status, &response.headers) {
+        return Err(error);
+    }
+
+    http::check_status(response)
+}
+
+/// Like `get`, but for downloads. The body is read as it comes in, and
+/// `headers` are sent along, like the ones that resume a download
+pub fn open(url: &str, headers: HeaderMap) -> Result<(HttpResponse, BodyReader), FetchError> {
+    let (response, body) = http::fetcher().open(url, with_token(url, headers)?)?;
+
+    if let Some(error) = rate_limit_error(response.status, &response.headers) {
+        return Err(error);
+    }
+
+    Ok((http::check_status(response)?, body))
+}
+
+/// Adds the token to the headers of a request to `url`, if it goes to GitHub
+fn with_token(url: &str, mut headers: HeaderMap) -> Result<HeaderMap, FetchError> {
+    if let Some(token) = token_for(url) {
+        let mut value = HeaderValue::from_str(&format!("Bearer {token}"))
+            .map_err(|e| FetchError::Permanent(t!("github.invalid_token", error = e)))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    Ok(headers)
+}
+
+/// The async version of `open`
+#[cfg(feature = "async")]
+pub async fn get_async(url: &str, headers: HeaderMap) -> Result<reqwest::Response, FetchError> {
+    let mut request = http::async_client().get(url).headers(headers);
+
+    if let Some(token) = token_for(url) {
+        request = request.bearer_auth(token);
+    }
+
+    let response = request.send().await?;
+
+    if let Some(error) = rate_limit_error(response.status(), response.headers()) {
+        return Err(error);
+    }
+
+    match http::status_error(response.status(), response.url()) {
+        Some(error) => Err(error),
+        None => Ok(response),
+    }
+}
+
+/// The token to send with a request to `url`. Only GitHub gets it
+fn token_for(url: &str) -> Option<&'static str> {
+    match TOKEN.get_or_init(|| None) {
+        Some(Token(token)) if is_github_url(url) => Some(token),
+        _ => None,
+    }
+}
+
+/// The names of the templates in the repo's `__fixtures__` directory
+pub fn list_templates(repo: &str, branch: &str) -> Vec<String> {
+    let url = format!("https://api.github.com/repos/{repo}/contents/__fixtures__?ref={branch}");
+
+    let entries: Value = http::with_retries(&t!("github.list_templates"), || get(&url)?.json());
+
+    entries
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.get("type").and_then(Value::as_str) == Some("dir"))
+        .filter_map(|entry| entry.get("name").and_then(Value::as_str))
+        .map(str::to_owned)
+        .collect()
+}
+
+fn is_github_url(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+
+    matches!(
+        url.host_str(),
+        Some("github.com" | "api.github.com" | "codeload.github.com")
+    )
+}
+
+/// GitHub has a primary rate limit, which resets once an hour, and secondary
+/// limits for bursts of requests, which tell you how long to back off. Only
+/// the latter are worth retrying
+fn rate_limit_error(status: StatusCode, headers: &HeaderMap) -> Option<FetchError> {
+    if !matches!(
+        status,
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+    ) {
+        return None;
+    }
+
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+
+    if let Some(retry_after) = header("retry-after") {
+        return Some(FetchError::Retryable(t!(
+            "github.secondary_rate_limit",
+            seconds = retry_after
+        )));
+    }
+
+    if header("x-ratelimit-remaining") != Some(0) {
+        return None;
+    }
+
+    let Some(reset) = header("x-ratelimit-reset") else {
+        return Some(FetchError::Permanent(format!(
+            "{} {}",
+            t!("github.rate_limit"),
+            t!("github.rate_limit_hint")
+        )));
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    let minutes = reset.saturating_sub(now).div_ceil(60);
+
+    Some(FetchError::Permanent(format!(
+        "{} {} {}",
+        t!("github.rate_limit"),
+        t!("github.rate_limit_resets", minutes = minutes),
+        t!("github.rate_limit_hint")
+    )))
+}
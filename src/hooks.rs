@@ -0,0 +1,77 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::events;
+use crate::exec::exec_streaming_in;
+use crate::i18n::t;
+use crate::package_manager::PackageManager;
+use crate::Config;
+
+/// Runs the `--post-hook` commands in the project, in order, through the
+/// shell. They run after the install, and before the initial commit, so
+/// what they change is included in it.
+///
+/// Their output is handled like the install's. A failing hook stops
+/// everything, so nothing is committed
+pub fn run_post_hooks(hooks: &[String], project_dir: &Path) {
+    if hooks.is_empty() {
+        return;
+    }
+
+    let phase = events::start("hooks");
+
+    for hook in hooks {
+        if Config::is_dry_run() {
+            events::status(t!(
+                "dry_run.post_hook",
+                hook = hook,
+                dir = project_dir.to_string_lossy()
+            ));
+            continue;
+        }
+
+        events::status(t!("progress.post_hook", hook = hook));
+        exec_streaming_in(&shell_command(hook), project_dir);
+    }
+
+    phase.finish(json!({ "count": hooks.len() }));
+}
+
+/// The command that installs the project's git hooks. That's its `prepare`
+/// script if it has one, as that's where husky and lefthook are usually set
+/// up, and yarn doesn't run it on install. Otherwise husky's or lefthook's
+/// own install command, if the project depends on either
+pub fn git_hooks_setup_command(
+    project_dir: &Path,
+    package_manager: PackageManager,
+) -> Option<Vec<String>> {
+    let contents = fs::read_to_string(project_dir.join("package.json")).ok()?;
+    let json: Value = serde_json::from_str(&contents).ok()?;
+
+    let has_dependency = |name: &str| {
+        ["dependencies", "devDependencies"]
+            .iter()
+            .any(|section| json.get(section).and_then(|deps| deps.get(name)).is_some())
+    };
+
+    let command: Vec<&str> = if json.pointer("/scripts/prepare").is_some() {
+        vec![package_manager.name(), "run", "prepare"]
+    } else if has_dependency("husky") {
+        [package_manager.exec_prefix(), &["husky"]].concat()
+    } else if has_dependency("lefthook") {
+        [package_manager.exec_prefix(), &["lefthook", "install"]].concat()
+    } else {
+        return None;
+    };
+
+    Some(command.into_iter().map(str::to_owned).collect())
+}
+
+fn shell_command(command: &str) -> [&str; 3] {
+    if cfg!(windows) {
+        ["cmd", "/C", command]
+    } else {
+        ["sh", "-c", command]
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// The message catalogs that ship with the quickstart, by language code.
+/// English is the one every other catalog falls back to, for anything it
+/// doesn't have (yet).
+///
+/// To add a translation, copy `locales/en.toml` to `locales/<code>.toml`,
+/// translate the values and add it here
+const CATALOGS: [(&str, &str); 1] = [("en", include_str!("../locales/en.toml"))];
+const FALLBACK: &str = "en";
+
+/// The messages in the selected language. Until `configure` is called, like
+/// while clap parses the arguments, there are only the English ones
+static SELECTED: OnceLock<HashMap<String, String>> = OnceLock::new();
+static ENGLISH: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Picks the language of the messages: `lang` if it's given, or the
+/// locale from `LC_ALL`, `LC_MESSAGES` or `LANG`, like `de_DE.UTF-8`. A
+/// locale we have no catalog for means English
+pub fn configure(lang: Option<&str>) {
+    let locale = lang.map(str::to_owned).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+    });
+
+    let code = locale.as_deref().and_then(catalog_for).unwrap_or(FALLBACK);
+    tracing::debug!("Showing messages in {code} (locale {locale:?})");
+
+    SELECTED.get_or_init(|| load(code));
+}
+
+/// Checks that there's a catalog for `--lang`, for clap
+pub fn parse_lang(lang: &str) -> Result<String, String> {
+    match catalog_for(lang) {
+        Some(_) => Ok(lang.to_owned()),
+        None => Err(message(
+            "errors.unknown_lang",
+            &[
+                ("lang", &lang),
+                ("available", &CATALOGS.map(|(code, _)| code).join(", ")),
+            ],
+        )),
+    }
+}
+
+/// The message for `key`, with each `{name}` in it replaced by its value
+/// in `args`. Use `t!` instead of calling this directly
+pub fn message(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let english = ENGLISH.get_or_init(|| load(FALLBACK));
+
+    let Some(template) = SELECTED
+        .get()
+        .and_then(|selected| selected.get(key))
+        .or_else(|| english.get(key))
+    else {
+        tracing::debug!("No message for {key}");
+        return key.to_owned();
+    };
+
+    // One pass, so a value with braces in it, like a path or a user's
+    // input, is never taken for a placeholder
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template.as_str();
+
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            args.iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|(_, value)| (end, value))
+        });
+
+        match value {
+            Some((end, value)) => {
+                message.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                message.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    message.push_str(rest);
+    message
+}
+
+/// The message for a key in the catalog, like `t!("done.run")`, or
+/// `t!("node.too_old", version = version)` with placeholders
+macro_rules! t {
+    ($key:literal) => {
+        $crate::i18n::message($key, &[])
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::message(
+            $key,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+
+pub(crate) use t;
+
+/// The code of the catalog for a locale like `pt_BR.UTF-8` or `pt-br`.
+/// Falls back from the region to the language, so `de_AT` gets `de`
+fn catalog_for(locale: &str) -> Option<&'static str> {
+    let locale = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-")
+        .to_ascii_lowercase();
+    let language = locale.split('-').next().unwrap_or_default();
+
+    let code = [locale.as_str(), language].into_iter().find_map(|wanted| {
+        CATALOGS
+            .iter()
+            .map(|(code, _)| *code)
+            .find(|code| code.eq_ignore_ascii_case(wanted))
+    });
+
+    code
+}
+
+/// The messages in a catalog, by their dotted keys, like `done.run` for
+/// `run` in the `[done]` table
+fn load(code: &str) -> HashMap<String, String> {
+    let (_, contents) = CATALOGS
+        .iter()
+        .find(|(catalog, _)| *catalog == code)
+        .expect("No such catalog");
+    let table: toml::Table = toml::from_str(contents).expect("Invalid message catalog");
+
+    let mut messages = HashMap::new();
+    flatten("", &table, &mut messages);
+
+    messages
+}
+
+fn flatten(prefix: &str, table: &toml::Table, messages: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match value {
+            toml::Value::Table(table) => flatten(&key, table, messages),
+            toml::Value::String(message) => {
+                messages.insert(key, message.clone());
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::exec::{
+    clear_status_line, display_command, exec_streaming_in_with_env, exec_streaming_watched,
+    Watchdog,
+};
+use crate::i18n::t;
+use crate::package_manager::PackageManager;
+use crate::{confirm, events, theme};
+
+/// The network concurrency a stalled install is retried with, one after
+/// the other. Fetching fewer packages at a time gets through flaky
+/// connections and registries that throttle
+const RETRY_NETWORK_CONCURRENCY: [u32; 2] = [8, 1];
+
+/// A `--install-env` variable. They're typically registry tokens, so the
+/// value is kept out of debug output
+#[derive(Clone)]
+pub struct EnvVar {
+    pub key: String,
+    pub value: String,
+}
+
+impl fmt::Debug for EnvVar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}=***", self.key)
+    }
+}
+
+/// What to do when the install stops printing anything
+#[derive(Debug)]
+pub struct StallOptions {
+    /// How long the install may go without output. `None` never treats it
+    /// as stalled
+    pub stall_after: Option<Duration>,
+    /// Kill and retry a stalled install without asking
+    pub auto_recover: bool,
+}
+
+/// Runs the install, watching it for stalls. A stalled install is killed
+/// and retried with a lower network concurrency if the user agrees, or
+/// right away with `auto_recover`. Returns the network concurrency the
+/// install got through with, if it had to be lowered
+pub fn run(
+    package_manager: PackageManager,
+    command: &[&str],
+    project_dir: &Path,
+    env: &[(String, String)],
+    options: &StallOptions,
+) -> Option<u32> {
+    let Some(stall_after) = options.stall_after else {
+        exec_streaming_in_with_env(command, project_dir, env);
+        return None;
+    };
+
+    let attempts: Vec<Option<u32>> = [None]
+        .into_iter()
+        .chain(RETRY_NETWORK_CONCURRENCY.map(Some))
+        .collect();
+
+    for (attempt, concurrency) in attempts.iter().enumerate() {
+        let (args, env) = with_network_concurrency(package_manager, command, env, *concurrency);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        if let Some(concurrency) = concurrency {
+            events::status(t!(
+                "install.retrying",
+                command = display_command(&args),
+                concurrency = concurrency
+            ));
+        }
+
+        let next = attempts.get(attempt + 1).copied().flatten();
+        // Without a next attempt there's nothing to offer, so the user is
+        // only told once, not every `stall_after`
+        let out_of_retries = AtomicBool::new(false);
+        let on_stall = |tail: &[String]| {
+            if next.is_none() && out_of_retries.swap(true, Ordering::Relaxed) {
+                return false;
+            }
+
+            on_stall(tail, stall_after, next, options.auto_recover)
+        };
+        let watchdog = Watchdog {
+            stall_after,
+            on_stall: &on_stall,
+        };
+
+        if exec_streaming_watched(&args, project_dir, &env, &watchdog).is_some() {
+            return *concurrency;
+        }
+    }
+
+    unreachable!("The last attempt is never killed for stalling");
+}
+
+/// Tells the user the install has stalled, and on what, and decides if it's
+/// killed to be retried with the `next` network concurrency. The last
+/// attempt has no `next`, and is left to `--command-timeout`
+fn on_stall(tail: &[String], stall_after: Duration, next: Option<u32>, auto_recover: bool) -> bool {
+    clear_status_line();
+    eprintln!(
+        "{}",
+        theme::warning(t!(
+            "install.stalled",
+            seconds = stall_after.as_secs(),
+            cause = likely_cause(tail)
+        ))
+    );
+
+    let Some(next) = next else {
+        eprintln!("{}", t!("install.out_of_retries"));
+        return false;
+    };
+
+    if auto_recover {
+        return true;
+    }
+
+    if confirm(&t!("install.retry_question", concurrency = next)) {
+        return true;
+    }
+
+    eprintln!("{}", t!("install.keep_waiting"));
+
+    false
+}
+
+/// What the install is likely stuck on, from the last lines it printed.
+/// Yarn prints a line per step, and names the packages it fetches and
+/// builds
+fn likely_cause(tail: &[String]) -> String {
+    for line in tail.iter().rev() {
+        let package = line
+            .split_whitespace()
+            .find(|word| word.contains("@npm:"))
+            .map(|word| word.trim_end_matches([',', ':']));
+
+        match package {
+            Some(package) if line.contains("must be built") || line.contains("YN0007") => {
+                return t!("install.cause.building", package = package);
+            }
+            Some(package) => return t!("install.cause.fetching", package = package),
+            None => {}
+        }
+
+        if line.contains("Resolution step") {
+            return t!("install.cause.resolution");
+        }
+        if line.contains("Fetch step") || line.contains("downloaded") {
+            return t!("install.cause.fetch");
+        }
+        if line.contains("Link step") {
+            return t!("install.cause.link");
+        }
+    }
+
+    match tail.last() {
+        Some(line) => t!("install.cause.last_line", line = line.trim()),
+        None => t!("install.cause.nothing"),
+    }
+}
+
+/// The install command and its environment, with the network concurrency
+/// lowered to `concurrency`. Yarn only takes it as a setting, which can be
+/// given in the environment
+fn with_network_concurrency(
+    package_manager: PackageManager,
+    command: &[&str],
+    env: &[(String, String)],
+    concurrency: Option<u32>,
+) -> (Vec<String>, Vec<(String, String)>) {
+    let mut args: Vec<String> = command.iter().map(|arg| (*arg).to_owned()).collect();
+    let mut env = env.to_vec();
+
+    let Some(concurrency) = concurrency else {
+        return (args, env);
+    };
+
+    match package_manager {
+        PackageManager::Yarn => {
+            env.push((
+                "YARN_NETWORK_CONCURRENCY".to_owned(),
+                concurrency.to_string(),
+            ));
+        }
+        PackageManager::Pnpm | PackageManager::Bun => {
+            args.push(format!("--network-concurrency={concurrency}"));
+        }
+        PackageManager::Npm => args.push(format!("--maxsockets={concurrency}")),
+    }
+
+    (args, env)
+}
@@ -0,0 +1,39 @@
+use serde_json::json;
+use std::path::Path;
+
+use crate::events;
+use crate::exec::{display_command, exec_streaming_in};
+use crate::i18n::t;
+use crate::package_manager::PackageManager;
+use crate::Config;
+
+/// The language the project is written in
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    /// TypeScript, which is what the templates are written in
+    Ts,
+    /// Plain JavaScript, converted from the TypeScript
+    Js,
+}
+
+/// Converts the project to JavaScript with Redwood's own `rw ts-to-js`,
+/// which strips the types, renames the files and replaces the tsconfig
+/// files with jsconfig files. It's part of the project's Redwood CLI, so the
+/// dependencies have to be installed first
+pub fn convert_to_js(package_manager: PackageManager, project_dir: &Path) {
+    let phase = events::start("convert");
+    let command = [package_manager.exec_prefix(), &["rw", "ts-to-js"]].concat();
+
+    if Config::is_dry_run() {
+        events::status(t!(
+            "dry_run.run",
+            command = display_command(&command),
+            dir = project_dir.to_string_lossy()
+        ));
+    } else {
+        events::status(t!("progress.convert_to_js"));
+        exec_streaming_in(&command, project_dir);
+    }
+
+    phase.finish(json!({ "language": "js" }));
+}
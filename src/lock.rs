@@ -0,0 +1,114 @@
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::i18n::t;
+use crate::{events, fs_util, theme};
+
+/// The dir in the cache dir that the locks are kept in
+pub const LOCKS_DIR_NAME: &str = "locks";
+
+/// An advisory lock, held until it's dropped. Other runs of the quickstart
+/// respect it, nothing else does. The OS releases it if we crash
+pub struct FileLock {
+    _file: File,
+}
+
+/// Takes the lock in `path`, if no other process holds it. The file is
+/// created if needed, and left behind afterwards, as removing it would race
+/// with whoever takes the lock next
+pub fn try_lock(path: &Path) -> io::Result<Option<FileLock>> {
+    let file = open(path)?;
+
+    match file.try_lock() {
+        Ok(()) => Ok(Some(FileLock { _file: file })),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Takes the lock in `path`, waiting for whoever holds it
+pub fn lock(path: &Path) -> io::Result<FileLock> {
+    let file = open(path)?;
+    file.lock()?;
+
+    Ok(FileLock { _file: file })
+}
+
+/// Makes sure no other run of the quickstart is creating a project in
+/// `installation_dir` at the same time. Exits if one is, unless `wait`, in
+/// which case it waits for it to finish. It's up to the caller to check
+/// what it left behind.
+///
+/// The lock is in the cache dir rather than next to the project, so it
+/// doesn't end up in it. `None` if it can't be taken, like on filesystems
+/// without locking, in which case we go ahead without it
+pub fn lock_target(installation_dir: &Path, wait: bool) -> Option<FileLock> {
+    let absolute = std::path::absolute(installation_dir).unwrap_or(installation_dir.to_path_buf());
+    let hash = format!(
+        "{:x}",
+        Sha256::digest(absolute.to_string_lossy().as_bytes())
+    );
+    let path = locks_dir().join(format!("{hash}.lock"));
+
+    let result = try_lock(&path).and_then(|lock| match lock {
+        Some(lock) => Ok(lock),
+        None if wait => {
+            events::status(t!("lock.waiting", dir = installation_dir.to_string_lossy()));
+            self::lock(&path)
+        }
+        None => {
+            eprintln!(
+                "{}",
+                theme::error(t!("lock.locked", dir = installation_dir.to_string_lossy()))
+            );
+            eprintln!("{}", t!("lock.locked_hint"));
+            std::process::exit(1);
+        }
+    });
+
+    match result {
+        Ok(lock) => Some(lock),
+        Err(e) => {
+            tracing::debug!("Could not lock {}: {e}", path.to_string_lossy());
+            None
+        }
+    }
+}
+
+/// Takes every lock in the locks dir, so no other run can start creating a
+/// project while they're held. `None` if another run holds one of them.
+/// Locks that can't be taken for any other reason are skipped
+pub fn try_lock_all() -> Option<Vec<FileLock>> {
+    let mut locks = Vec::new();
+
+    for entry in fs::read_dir(locks_dir()).into_iter().flatten().flatten() {
+        match try_lock(&entry.path()) {
+            Ok(Some(lock)) => locks.push(lock),
+            Ok(None) => return None,
+            Err(e) => tracing::debug!("Could not lock {}: {e}", entry.path().to_string_lossy()),
+        }
+    }
+
+    Some(locks)
+}
+
+/// `<cache dir>/locks`, or a dir in the temp dir if there's no cache dir
+fn locks_dir() -> PathBuf {
+    fs_util::cache_dir()
+        .unwrap_or_else(|| std::env::temp_dir().join("rw-rsc-quickstart"))
+        .join(LOCKS_DIR_NAME)
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+}
@@ -0,0 +1,86 @@
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use crate::exec::{display_command, exec_streaming_in_with_env};
+use crate::i18n::t;
+use crate::package_manager::PackageManager;
+use crate::{events, Config};
+
+/// What to do with the project's lockfile
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lockfile {
+    /// Keep the fixture's lockfile. The install only updates what the
+    /// version bump changed
+    Keep,
+    /// Remove the fixture's lockfile after the version bump, and resolve
+    /// every dependency from scratch
+    Regenerate,
+    /// Keep the lockfile, but leave it out of the initial commit
+    SkipCommit,
+}
+
+/// The lockfile the package manager writes, in the project's root
+pub fn file_name(package_manager: PackageManager) -> &'static str {
+    match package_manager {
+        PackageManager::Yarn => "yarn.lock",
+        PackageManager::Pnpm => "pnpm-lock.yaml",
+        PackageManager::Npm => "package-lock.json",
+        PackageManager::Bun => "bun.lock",
+    }
+}
+
+/// The command that writes the lockfile without installing anything. Bun
+/// has none, so its lockfile is written by the install
+fn lockfile_only_command(package_manager: PackageManager) -> Option<[&'static str; 3]> {
+    match package_manager {
+        PackageManager::Yarn => Some(["yarn", "install", "--mode=update-lockfile"]),
+        PackageManager::Pnpm => Some(["pnpm", "install", "--lockfile-only"]),
+        PackageManager::Npm => Some(["npm", "install", "--package-lock-only"]),
+        PackageManager::Bun => None,
+    }
+}
+
+/// Removes the project's lockfile, and resolves every dependency again
+/// into a new one, without installing them. That happens even with
+/// `--skip-install`, so the project still gets a lockfile that matches
+/// the bumped versions.
+///
+/// Only the package manager's own lockfile is removed. The fixture's
+/// `yarn.lock` is left alone for the other package managers, which don't
+/// read it
+pub fn regenerate(package_manager: PackageManager, project_dir: &Path, env: &[(String, String)]) {
+    let phase = events::start("lockfile");
+    let file_name = file_name(package_manager);
+    let command = lockfile_only_command(package_manager);
+    let dir = project_dir.to_string_lossy();
+
+    if Config::is_dry_run() {
+        events::status(t!("dry_run.remove", file = file_name, dir = dir));
+        if let Some(command) = command {
+            events::status(t!(
+                "dry_run.run",
+                command = display_command(&command),
+                dir = dir
+            ));
+        }
+        phase.finish(json!({ "lockfile": file_name }));
+        return;
+    }
+
+    let path = project_dir.join(file_name);
+    if path.exists() {
+        tracing::debug!("Removing {}", path.to_string_lossy());
+        fs::remove_file(&path).expect("Failed to remove the lockfile");
+    }
+
+    if let Some(command) = command {
+        events::status(t!(
+            "progress.regenerate_lockfile",
+            command = display_command(&command)
+        ));
+        exec_streaming_in_with_env(&command, project_dir, env);
+    }
+
+    phase.finish(json!({ "lockfile": file_name }));
+}
@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+use tracing::Subscriber;
+use tracing_subscriber::field::MakeExt;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::format::{debug_fn, FmtSpan};
+use tracing_subscriber::fmt::time::Uptime;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::diagnostics::RecentLines;
+use crate::i18n::t;
+
+/// Target for the progress messages printed to stdout. They're only written
+/// to the log files, as they're already shown on the terminal
+pub const STATUS_TARGET: &str = "status";
+
+/// Sets up logging to stderr, and to `log_file` if given.
+///
+/// What's logged to stderr depends on `verbosity`, the number of `-v`s:
+/// warnings by default, debug messages and phase timings with `-v`, every
+/// command and request with `-vv`, and the same for our dependencies (like
+/// the http client) with `-vvv`. `quiet` only lets errors through.
+///
+/// The log file always gets debug level output, no matter the verbosity
+pub fn init(verbosity: u8, quiet: bool, log_file: Option<&Path>) {
+    let crate_level = match (quiet, verbosity) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let dependency_level = if verbosity >= 3 {
+        LevelFilter::TRACE
+    } else {
+        LevelFilter::WARN
+    };
+
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(console::colors_enabled_stderr())
+        .with_target(false)
+        .with_timer(Uptime::default())
+        .with_span_events(FmtSpan::CLOSE)
+        .with_filter(
+            Targets::new()
+                .with_target(env!("CARGO_CRATE_NAME"), crate_level)
+                .with_default(dependency_level),
+        );
+
+    let file_layer = log_file.map(|path| {
+        let file = File::create(path).unwrap_or_else(|e| {
+            eprintln!(
+                "{}",
+                t!(
+                    "errors.cant_create_log",
+                    file = path.to_string_lossy(),
+                    error = e
+                )
+            );
+            std::process::exit(1);
+        });
+
+        plain_layer(Mutex::new(file), LevelFilter::DEBUG)
+    });
+
+    // Kept in memory for the debug log that's written if something fails.
+    // Includes every command that's run
+    let recent_lines_layer = plain_layer(|| RecentLines, LevelFilter::TRACE);
+
+    tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(file_layer)
+        .with(recent_lines_layer)
+        .init();
+}
+
+/// A layer without colors, with timestamps, for logs that are written to
+/// files
+fn plain_layer<S, W>(writer: W, crate_level: LevelFilter) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        // Span fields are formatted once per field formatter type. Using a
+        // different one than the stderr layer keeps colors out of the file
+        .fmt_fields(
+            debug_fn(|writer, field, value| match field.name() {
+                "message" => write!(writer, "{value:?}"),
+                name => write!(writer, "{name}={value:?}"),
+            })
+            .delimited(" "),
+        )
+        .with_span_events(FmtSpan::CLOSE)
+        .with_filter(
+            Targets::new()
+                .with_target(env!("CARGO_CRATE_NAME"), crate_level)
+                .with_target(STATUS_TARGET, LevelFilter::INFO)
+                .with_default(LevelFilter::WARN),
+        )
+}
@@ -0,0 +1,148 @@
+use reqwest::header::HeaderMap;
+use reqwest::{StatusCode, Url};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::exec::{self, CommandRunner, Killed, Watchdog};
+use crate::http::{self, FetchError, Fetcher, HttpResponse};
+
+/// Points to the directory with the canned responses. Setting it makes the
+/// whole run go through `MockFetcher` and `MockRunner`
+const MOCK_DIR_ENV_VAR: &str = "RW_QUICKSTART_MOCK_DIR";
+
+/// Answers requests with the files in `<dir>/http`, without touching the
+/// network. `https://registry.npmjs.org/react` is answered with
+/// `<dir>/http/registry.npmjs.org/react`, and any url without a file gets a
+/// 404. Every request is logged to `<dir>/requests.log`
+pub struct MockFetcher {
+    dir: PathBuf,
+}
+
+/// Records commands in `<dir>/commands.log` instead of running them. They
+/// all succeed, and print what's in `<dir>/commands/<program>`, if there is
+/// such a file
+pub struct MockRunner {
+    dir: PathBuf,
+}
+
+/// Replaces the fetcher and the command runner with mocks, if
+/// `RW_QUICKSTART_MOCK_DIR` is set. For running the full pipeline in tests,
+/// without a network and without node or yarn installed
+pub fn install_from_env() {
+    let Some(dir) = std::env::var_os(MOCK_DIR_ENV_VAR).map(PathBuf::from) else {
+        return;
+    };
+
+    tracing::debug!("Using the mocks in {}", dir.to_string_lossy());
+
+    http::set_fetcher(Box::leak(Box::new(MockFetcher { dir: dir.clone() })));
+    exec::set_runner(Box::leak(Box::new(MockRunner { dir })));
+}
+
+impl MockFetcher {
+    /// Logs the request, and answers it with its file
+    fn respond(&self, method: &str, url: &str) -> Result<HttpResponse, FetchError> {
+        log(&self.dir.join("requests.log"), &format!("{method} {url}"));
+
+        let url = Url::parse(url).map_err(|e| FetchError::Permanent(format!("{url}: {e}")))?;
+        let path = self
+            .dir
+            .join("http")
+            .join(url.host_str().unwrap_or_default())
+            .join(url.path().trim_start_matches('/'));
+
+        let (status, body) = match fs::read(&path) {
+            Ok(body) => (StatusCode::OK, body),
+            Err(_) => (StatusCode::NOT_FOUND, Vec::new()),
+        };
+
+        Ok(HttpResponse {
+            status,
+            url,
+            headers: HeaderMap::new(),
+            body,
+        })
+    }
+}
+
+impl Fetcher for MockFetcher {
+    fn get(&self, url: &str, _headers: HeaderMap) -> Result<HttpResponse, FetchError> {
+        self.respond("GET", url)
+    }
+
+    fn head(&self, url: &str, _timeout: Duration) -> Result<HttpResponse, FetchError> {
+        self.respond("HEAD", url).map(|response| HttpResponse {
+            body: Vec::new(),
+            ..response
+        })
+    }
+}
+
+impl MockRunner {
+    /// Logs the command, and returns what it should print
+    fn run(&self, command: &Command) -> Vec<u8> {
+        let program = command.get_program().to_string_lossy();
+        let args: Vec<_> = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy())
+            .collect();
+        let args: Vec<&str> = std::iter::once(program.as_ref())
+            .chain(args.iter().map(|arg| arg.as_ref()))
+            .collect();
+        let cwd = command
+            .get_current_dir()
+            .map(|cwd| cwd.to_string_lossy())
+            .unwrap_or_default();
+
+        log(
+            &self.dir.join("commands.log"),
+            &format!("{} (in {cwd})", exec::display_command(&args)),
+        );
+
+        let name = Path::new(program.as_ref())
+            .file_name()
+            .unwrap_or_default()
+            .to_owned();
+
+        fs::read(self.dir.join("commands").join(name)).unwrap_or_default()
+    }
+}
+
+impl CommandRunner for MockRunner {
+    fn output(&self, command: &mut Command) -> Result<Output, Killed> {
+        Ok(Output {
+            status: ExitStatus::default(),
+            stdout: self.run(command),
+            stderr: Vec::new(),
+        })
+    }
+
+    fn stream(
+        &self,
+        command: &mut Command,
+        lines: mpsc::Sender<String>,
+        _watchdog: Option<&Watchdog>,
+    ) -> Result<ExitStatus, Killed> {
+        let stdout = self.run(command);
+
+        for line in String::from_utf8_lossy(&stdout).lines() {
+            lines.send(line.to_owned()).ok();
+        }
+
+        Ok(ExitStatus::default())
+    }
+}
+
+fn log(path: &Path, line: &str) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .expect("Failed to open the mock log");
+
+    writeln!(file, "{line}").expect("Failed to write to the mock log");
+}
@@ -0,0 +1,351 @@
+use semver_rs::satisfies;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::exec::{display_command, exec, output_with_timeout};
+use crate::i18n::t;
+use crate::{confirm, diagnostics, events, theme, Config, MIN_NODE_VERSION};
+
+/// Node version managers we know how to drive, in the order we prefer them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionManager {
+    Nvm,
+    Fnm,
+    Volta,
+    Asdf,
+    Mise,
+}
+
+impl VersionManager {
+    const ALL: [VersionManager; 5] = [
+        VersionManager::Nvm,
+        VersionManager::Fnm,
+        VersionManager::Volta,
+        VersionManager::Asdf,
+        VersionManager::Mise,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VersionManager::Nvm => "nvm",
+            VersionManager::Fnm => "fnm",
+            VersionManager::Volta => "volta",
+            VersionManager::Asdf => "asdf",
+            VersionManager::Mise => "mise",
+        }
+    }
+
+    /// The version managers that are installed. nvm is a shell function, not
+    /// a binary, so it's found through its install dir instead of PATH
+    pub fn detect() -> Vec<VersionManager> {
+        VersionManager::ALL
+            .into_iter()
+            .filter(|manager| match manager {
+                VersionManager::Nvm => nvm_script().is_some(),
+                _ => which::which(manager.name()).is_ok(),
+            })
+            .collect()
+    }
+
+    /// What the user should run to install and switch to `version`. It comes
+    /// from the project's `.nvmrc`, so it's quoted like in `install_script`
+    fn switch_command(&self, version: &str) -> String {
+        match self {
+            VersionManager::Nvm => {
+                let version = shell_quote(version);
+                format!("nvm install {version} && nvm use {version}")
+            }
+            VersionManager::Fnm => {
+                let version = shell_quote(version);
+                format!("fnm install {version} && fnm use {version}")
+            }
+            VersionManager::Volta => {
+                format!("volta install {}", shell_quote(&format!("node@{version}")))
+            }
+            VersionManager::Asdf => {
+                let version = shell_quote(&format!("latest:{version}"));
+                format!("asdf install nodejs {version} && asdf set nodejs {version}")
+            }
+            VersionManager::Mise => {
+                format!(
+                    "mise use --global {}",
+                    shell_quote(&format!("node@{version}"))
+                )
+            }
+        }
+    }
+
+    /// A bash script that installs `version` and prints the path to its
+    /// `node` binary. Installing is all we do, the user's default version
+    /// isn't changed. `version` comes from the project's `.nvmrc`, so it's
+    /// quoted everywhere rather than trusted to be just a version
+    fn install_script(&self, version: &str) -> String {
+        match self {
+            VersionManager::Nvm => {
+                // Sourced from where we found it, as NVM_DIR isn't set when
+                // nvm is in ~/.nvm
+                let script = nvm_script().expect("nvm was detected without its script");
+                let version = shell_quote(version);
+                format!(
+                    ". {} && nvm install {version} >&2 && nvm which {version}",
+                    shell_quote(&script.to_string_lossy())
+                )
+            }
+            VersionManager::Fnm => {
+                let using = shell_quote(&format!("--using={version}"));
+                let version = shell_quote(version);
+                format!(
+                    "fnm install {version} >&2 && \
+                    fnm exec {using} -- node -p process.execPath"
+                )
+            }
+            VersionManager::Volta => format!(
+                "volta install {} >&2 && volta which node",
+                shell_quote(&format!("node@{version}"))
+            ),
+            VersionManager::Asdf => {
+                let latest = shell_quote(&format!("latest:{version}"));
+                let version = shell_quote(version);
+                format!(
+                    "asdf install nodejs {latest} >&2 && \
+                    echo \"$(asdf where nodejs \"$(asdf latest nodejs {version})\")/bin/node\""
+                )
+            }
+            VersionManager::Mise => {
+                let node = shell_quote(&format!("node@{version}"));
+                format!(
+                    "mise install {node} >&2 && \
+                    mise exec {node} -- node -p process.execPath"
+                )
+            }
+        }
+    }
+}
+
+/// The installed Node version, like `v20.11.0`, or `None` if there's no
+/// `node` in PATH
+pub fn version() -> Option<String> {
+    which::which("node").ok()?;
+    Some(exec(&["node", "--version"]).trim().to_owned())
+}
+
+/// The Node version the project wants, from `.nvmrc` or the major version of
+/// `engines.node` in package.json. Falls back to our own minimum
+pub fn required_version(project_dir: Option<&Path>) -> String {
+    let from_project = project_dir.and_then(|dir| {
+        let nvmrc = fs::read_to_string(dir.join(".nvmrc"))
+            .ok()
+            .map(|nvmrc| nvmrc.trim().trim_start_matches('v').to_owned())
+            .filter(|version| !version.is_empty());
+
+        nvmrc.or_else(|| engines_range(dir).as_deref().and_then(first_number))
+    });
+
+    from_project
+        .or_else(|| first_number(MIN_NODE_VERSION))
+        .unwrap_or_else(|| "20".to_owned())
+}
+
+/// Makes sure Node is installed and new enough. With `fix` a missing or
+/// outdated Node is installed with the user's version manager. Otherwise we
+/// explain how to do that and exit
+pub fn check(project_dir: Option<&Path>, fix: bool) {
+    if let Some(version) = version() {
+        tracing::debug!("Node version: {version}");
+
+        // Compare semver versions. Node has to be at least v 20
+        if satisfies(&version, MIN_NODE_VERSION, None).unwrap() {
+            return;
+        }
+
+        eprintln!("{}", theme::error(t!("node.too_old", version = version)));
+    } else {
+        eprintln!("{}", theme::error(t!("node.not_found")));
+    }
+
+    let required = required_version(project_dir);
+
+    if !(fix && install_required(&required, false)) {
+        print_guidance(&required, &VersionManager::detect());
+        diagnostics::fail();
+    }
+}
+
+/// Makes sure Node satisfies the project's `engines.node` range. Redwood can
+/// require a newer Node than our own minimum, and the project is the one
+/// that knows. Runs after the project has been extracted
+pub fn check_engines(project_dir: &Path, fix: bool) {
+    let (Some(range), Some(version)) = (engines_range(project_dir), version()) else {
+        return;
+    };
+
+    let satisfied = |version: &str| {
+        satisfies(version, &range, None).unwrap_or_else(|e| {
+            tracing::debug!("Ignoring engines.node `{range}`: {e:?}");
+            true
+        })
+    };
+
+    if satisfied(&version) {
+        return;
+    }
+
+    eprintln!(
+        "{}",
+        theme::error(t!("node.engines", version = version, range = range))
+    );
+
+    let required = required_version(Some(project_dir));
+
+    if fix && install_required(&required, false) && self::version().is_some_and(|v| satisfied(&v)) {
+        return;
+    }
+
+    print_guidance(&required, &VersionManager::detect());
+    diagnostics::fail();
+}
+
+/// Installs `required` with the first version manager we find. With `ask`
+/// the user has to confirm first. Returns whether we now have a new enough
+/// Node. A dry run only says what it would install
+pub fn install_required(required: &str, ask: bool) -> bool {
+    let Some(manager) = VersionManager::detect().into_iter().next() else {
+        return false;
+    };
+
+    if Config::is_dry_run() {
+        events::status(t!(
+            "dry_run.install_node",
+            version = required,
+            manager = manager.name()
+        ));
+        return true;
+    }
+
+    let question = t!(
+        "node.install_question",
+        version = required,
+        manager = manager.name()
+    );
+
+    (!ask || confirm(&question)) && install(manager, required)
+}
+
+/// Explains how to get `required`, using the version managers the user has
+pub fn print_guidance(required: &str, managers: &[VersionManager]) {
+    eprintln!("{}", guidance(required, managers));
+}
+
+/// What `print_guidance` prints
+pub fn guidance(required: &str, managers: &[VersionManager]) -> String {
+    let mut lines = match managers {
+        [] => vec![t!("node.install_hint", version = required)],
+        [manager] => vec![
+            t!(
+                "node.switch_hint",
+                manager = manager.name(),
+                version = required
+            ),
+            format!("`{}`", manager.switch_command(required)),
+        ],
+        managers => std::iter::once(t!("node.switch_hint_managers", version = required))
+            .chain(managers.iter().map(|manager| {
+                format!(
+                    "  {}: `{}`",
+                    manager.name(),
+                    manager.switch_command(required)
+                )
+            }))
+            .collect(),
+    };
+
+    lines.push(t!("node.fix_hint"));
+    lines.join("\n")
+}
+
+/// Installs `version` with `manager`, and puts it first in PATH for the rest
+/// of the run, including the commands we start
+fn install(manager: VersionManager, version: &str) -> bool {
+    if cfg!(windows) {
+        eprintln!("{}", t!("node.install_unsupported"));
+        return false;
+    }
+
+    let script = manager.install_script(version);
+    let args = ["bash", "-c", script.as_str()];
+
+    eprintln!(
+        "{}",
+        t!(
+            "node.installing",
+            version = version,
+            manager = manager.name()
+        )
+    );
+
+    let mut command = std::process::Command::new("bash");
+    command.args(&args[1..]);
+    let output = output_with_timeout(&mut command, &display_command(&args));
+
+    if !output.status.success() {
+        eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+        eprintln!(
+            "{}",
+            theme::warning(t!("node.install_failed", manager = manager.name()))
+        );
+        return false;
+    }
+
+    let node = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    let Some(bin_dir) = node.parent() else {
+        return false;
+    };
+
+    tracing::debug!("Using Node from {}", bin_dir.to_string_lossy());
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let paths = std::iter::once(bin_dir.to_path_buf()).chain(std::env::split_paths(&path));
+    let path = std::env::join_paths(paths).expect("Failed to update PATH");
+    std::env::set_var("PATH", path);
+
+    match self::version() {
+        Some(version) if satisfies(&version, MIN_NODE_VERSION, None).unwrap() => {
+            eprintln!("{}", t!("node.using", version = version));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The `engines.node` range in the project's package.json
+fn engines_range(project_dir: &Path) -> Option<String> {
+    let package_json = fs::read_to_string(project_dir.join("package.json")).ok()?;
+    let json: Value = serde_json::from_str(&package_json).ok()?;
+
+    json.pointer("/engines/node")?.as_str().map(str::to_owned)
+}
+
+fn nvm_script() -> Option<PathBuf> {
+    let nvm_dir = std::env::var_os("NVM_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".nvm")))?;
+
+    Some(nvm_dir.join("nvm.sh")).filter(|script| script.exists())
+}
+
+/// Quotes `value` for a POSIX shell, so it's passed on as-is
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// The first number in a version range, like `20` in `>=20.10`
+fn first_number(range: &str) -> Option<String> {
+    let start = range.find(|c: char| c.is_ascii_digit())?;
+    let number: String = range[start..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+
+    Some(number)
+}
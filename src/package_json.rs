@@ -0,0 +1,433 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::diagnostics;
+use crate::events;
+use crate::i18n::t;
+use crate::registry;
+use crate::theme;
+use crate::Config;
+
+/// The sections of package.json that map package names to versions
+const DEPENDENCY_SECTIONS: [&str; 6] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+    "resolutions",
+    "overrides",
+];
+
+/// How a JSON file is formatted, so it can be written back the same way
+#[derive(Debug)]
+pub struct JsonStyle {
+    indent: String,
+    crlf: bool,
+    trailing_newline: bool,
+}
+
+impl JsonStyle {
+    /// Detects the style from the file's contents. Defaults to what npm
+    /// writes: two spaces, `\n` line endings and a trailing newline
+    fn detect(contents: &str) -> JsonStyle {
+        let indent = contents
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let content_start = line.len() - line.trim_start_matches([' ', '\t']).len();
+                &line[..content_start]
+            })
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("  ");
+
+        JsonStyle {
+            indent: indent.to_owned(),
+            crlf: contents.contains("\r\n"),
+            trailing_newline: contents.is_empty() || contents.ends_with('\n'),
+        }
+    }
+}
+
+impl Default for JsonStyle {
+    fn default() -> Self {
+        JsonStyle::detect("")
+    }
+}
+
+/// Reads and parses a package.json file, keeping track of its formatting
+pub fn read(path: &Path) -> (Value, JsonStyle) {
+    let contents = fs::read_to_string(path).expect("Failed to read file");
+    let json = serde_json::from_str(&contents).expect("Failed to parse json");
+
+    (json, JsonStyle::detect(&contents))
+}
+
+/// Writes `json` to `path` formatted in `style`, so that a rewrite only
+/// changes the values that actually changed
+pub fn write(path: &Path, json: &Value, style: &JsonStyle) {
+    let mut buf = Vec::new();
+    let formatter = PrettyFormatter::with_indent(style.indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+    json.serialize(&mut serializer)
+        .expect("Failed to serialize json");
+
+    let mut contents = String::from_utf8(buf).expect("Serialized json is not utf-8");
+
+    if style.trailing_newline {
+        contents.push('\n');
+    }
+
+    if style.crlf {
+        contents = contents.replace('\n', "\r\n");
+    }
+
+    fs::write(path, contents).expect("Failed to write file");
+}
+
+/// A Redwood dependency whose version was changed
+#[derive(Debug)]
+pub struct Change {
+    pub path: PathBuf,
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// The package.json files under `dir`, for `update_package_jsons`.
+/// node_modules is full of package.json files we shouldn't touch, so it's
+/// skipped
+pub fn find_package_jsons(dir: &str) -> impl Iterator<Item = Result<PathBuf, glob::GlobError>> {
+    glob::glob(&format!("{dir}/**/package.json"))
+        .expect("Failed to glob")
+        .filter(|entry| {
+            entry.as_ref().map_or(true, |path| {
+                !path.components().any(|c| c.as_os_str() == "node_modules")
+            })
+        })
+}
+
+/// Prints each changed file, relative to `dir`, with its old and new
+/// versions like a diff
+pub fn print_changes(dir: &Path, changes: &[Change]) {
+    let mut by_file: BTreeMap<&Path, Vec<&Change>> = BTreeMap::new();
+    for change in changes {
+        by_file.entry(&change.path).or_default().push(change);
+    }
+
+    for (path, changes) in by_file {
+        events::status(theme::dim(
+            path.strip_prefix(dir).unwrap_or(path).to_string_lossy(),
+        ));
+
+        for change in changes {
+            events::status(theme::removed(format!(
+                "-  \"{}\": \"{}\"",
+                change.name, change.from
+            )));
+            events::status(theme::added(format!(
+                "+  \"{}\": \"{}\"",
+                change.name, change.to
+            )));
+        }
+
+        events::status("");
+    }
+}
+
+/// Redwood versions left behind by an update, and where
+type RwVersions = BTreeMap<String, BTreeSet<String>>;
+
+/// Sets all @redwoodjs/* packages in the given package.json files to
+/// `latest_rw_canary`, returning what was changed, file by file. The files
+/// are patched in parallel, and only written if something changed.
+///
+/// Only registry dependencies are touched. The project's own workspace
+/// packages, and anything using the `workspace:` protocol, are linked
+/// locally and are left as they are
+///
+/// With `check_registry` it exits without changing anything if any of the
+/// packages doesn't have that version. It always exits if the Redwood
+/// versions are inconsistent afterwards
+pub fn update_package_jsons<I>(
+    package_jsons: I,
+    latest_rw_canary: String,
+    check_registry: bool,
+) -> Vec<Change>
+where
+    I: Iterator<Item = Result<PathBuf, glob::GlobError>>,
+{
+    let start = Instant::now();
+
+    let paths: Vec<PathBuf> = package_jsons
+        .map(|entry| entry.expect("Failed to get path"))
+        .collect();
+
+    let workspace_packages = root_dir(&paths).map(workspace_packages).unwrap_or_default();
+
+    if check_registry {
+        verify_versions_exist(&paths, &latest_rw_canary, &workspace_packages);
+    }
+
+    let (rw_versions, mut changes) = paths
+        .par_iter()
+        .map(|path| update_package_json(path, &latest_rw_canary, &workspace_packages))
+        .reduce(
+            || (RwVersions::new(), Vec::new()),
+            |(mut all_versions, mut all_changes), (versions, changes)| {
+                for (version, paths) in versions {
+                    all_versions.entry(version).or_default().extend(paths);
+                }
+                all_changes.extend(changes);
+                (all_versions, all_changes)
+            },
+        );
+
+    tracing::debug!(
+        "Updated {} package.json files in {:?}",
+        paths.len(),
+        start.elapsed()
+    );
+
+    if rw_versions.len() > 1 {
+        eprintln!("{}", theme::error(t!("package_json.mixed_versions")));
+        for (version, paths) in rw_versions {
+            eprintln!("  {version}:");
+            for path in paths {
+                eprintln!("    {path}");
+            }
+        }
+        diagnostics::fail();
+    }
+
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// Makes sure every Redwood package in `paths` has `version` on the
+/// registry. Packages don't all publish on the same cadence, and this gives
+/// a much clearer error than the install failing
+fn verify_versions_exist(paths: &[PathBuf], version: &str, workspace_packages: &BTreeSet<String>) {
+    let packages: BTreeSet<String> = paths
+        .iter()
+        .flat_map(|path| {
+            let (json, _) = read(path);
+            rw_package_names(&json, workspace_packages)
+        })
+        .collect();
+
+    tracing::debug!("Checking that {} packages have {version}", packages.len());
+
+    let missing: Vec<&String> = packages
+        .par_iter()
+        .filter(|package| !registry::has_version(package, version))
+        .collect();
+
+    if missing.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "{}",
+        theme::error(t!("package_json.missing_versions", version = version))
+    );
+    for package in missing {
+        eprintln!("  {package}");
+    }
+    eprintln!("{}", t!("package_json.missing_versions_hint"));
+    diagnostics::fail();
+}
+
+/// The names of the Redwood packages in all dependency sections. Keys like
+/// `**/@redwoodjs/core` are reduced to the package name
+fn rw_package_names(json: &Value, workspace_packages: &BTreeSet<String>) -> Vec<String> {
+    DEPENDENCY_SECTIONS
+        .iter()
+        .filter_map(|section| json.get(section))
+        .chain(json.pointer("/pnpm/overrides"))
+        .filter_map(Value::as_object)
+        .flat_map(|dependencies| dependencies.iter())
+        .filter(|(name, value)| is_rw_dependency(name, value, workspace_packages))
+        .filter_map(|(name, _)| name.find("@redwoodjs/").map(|i| name[i..].to_owned()))
+        .collect()
+}
+
+/// Updates a single package.json, returning the Redwood versions it ends up
+/// with, and what changed
+fn update_package_json(
+    path: &Path,
+    latest_rw_canary: &str,
+    workspace_packages: &BTreeSet<String>,
+) -> (RwVersions, Vec<Change>) {
+    events::detail(t!("package_json.updating", file = path.to_string_lossy()));
+
+    let (mut json, style) = read(path);
+    let mut changes = Vec::new();
+
+    for section in DEPENDENCY_SECTIONS {
+        if let Some(dependencies) = json.get_mut(section) {
+            changes.extend(update_section(
+                path,
+                dependencies,
+                latest_rw_canary,
+                workspace_packages,
+            ));
+        }
+    }
+
+    // pnpm reads its overrides from its own config section
+    if let Some(overrides) = json.pointer_mut("/pnpm/overrides") {
+        changes.extend(update_section(
+            path,
+            overrides,
+            latest_rw_canary,
+            workspace_packages,
+        ));
+    }
+
+    let mut rw_versions = BTreeMap::new();
+    collect_rw_versions(&json, path, workspace_packages, &mut rw_versions);
+
+    if !changes.is_empty() && !Config::is_dry_run() {
+        write(path, &json, &style);
+    }
+
+    (rw_versions, changes)
+}
+
+/// Redwood packages are matched on the end of the key, so that resolutions
+/// like `**/@redwoodjs/core` are updated as well. Nested overrides (objects)
+/// are left alone
+fn update_section(
+    path: &Path,
+    dependencies: &mut Value,
+    version: &str,
+    workspace_packages: &BTreeSet<String>,
+) -> Vec<Change> {
+    let Some(dependencies) = dependencies.as_object_mut() else {
+        return Vec::new();
+    };
+
+    let mut changes = Vec::new();
+
+    for (name, value) in dependencies.iter_mut() {
+        let Some(from) = value.as_str().filter(|from| *from != version) else {
+            continue;
+        };
+
+        if is_rw_dependency(name, value, workspace_packages) {
+            changes.push(Change {
+                path: path.to_path_buf(),
+                name: name.clone(),
+                from: from.to_owned(),
+                to: version.to_owned(),
+            });
+            *value = Value::String(version.to_owned());
+        }
+    }
+
+    changes
+}
+
+fn is_rw_package(name: &str) -> bool {
+    name.starts_with("@redwoodjs/") || name.contains("/@redwoodjs/")
+}
+
+/// A Redwood package that's installed from the registry, as opposed to one
+/// of the project's own workspace packages
+fn is_rw_dependency(name: &str, value: &Value, workspace_packages: &BTreeSet<String>) -> bool {
+    let Some(version) = value.as_str() else {
+        return false;
+    };
+
+    let package = name.find("@redwoodjs/").map_or(name, |i| &name[i..]);
+
+    is_rw_package(name)
+        && !version.starts_with("workspace:")
+        && !workspace_packages.contains(package)
+}
+
+/// The dir of the root package.json, which is the one closest to the top
+fn root_dir(paths: &[PathBuf]) -> Option<&Path> {
+    paths
+        .iter()
+        .min_by_key(|path| path.components().count())
+        .and_then(|path| path.parent())
+}
+
+/// The names of the project's own packages, which are in its workspaces
+pub fn workspace_packages(root_dir: &Path) -> BTreeSet<String> {
+    let packages: BTreeSet<String> = workspace_dirs(root_dir)
+        .into_iter()
+        .filter_map(|dir| fs::read_to_string(dir.join("package.json")).ok())
+        .filter_map(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .filter_map(|json| json.get("name").and_then(Value::as_str).map(str::to_owned))
+        .collect();
+
+    tracing::debug!("Workspace packages: {packages:?}");
+
+    packages
+}
+
+/// The dirs matched by the root package.json's `workspaces` that have a
+/// package.json. Both the array form and Yarn's `{ "packages": [...] }` are
+/// supported. Negated globs are ignored, which can only make us skip more
+/// packages than needed, never rewrite a workspace package
+pub fn workspace_dirs(root_dir: &Path) -> Vec<PathBuf> {
+    let Ok(contents) = fs::read_to_string(root_dir.join("package.json")) else {
+        return Vec::new();
+    };
+    let Ok(json) = serde_json::from_str::<Value>(&contents) else {
+        return Vec::new();
+    };
+
+    let workspaces = json
+        .get("workspaces")
+        .map(|workspaces| workspaces.get("packages").unwrap_or(workspaces))
+        .and_then(Value::as_array);
+
+    workspaces
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter(|pattern| !pattern.starts_with('!'))
+        .filter_map(|pattern| {
+            let pattern = root_dir.join(pattern).join("package.json");
+            glob::glob(&pattern.to_string_lossy()).ok()
+        })
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|path| path.parent().map(Path::to_path_buf))
+        .collect()
+}
+
+fn collect_rw_versions(
+    json: &Value,
+    path: &Path,
+    workspace_packages: &BTreeSet<String>,
+    rw_versions: &mut BTreeMap<String, BTreeSet<String>>,
+) {
+    let sections = DEPENDENCY_SECTIONS
+        .iter()
+        .filter_map(|section| json.get(section))
+        .chain(json.pointer("/pnpm/overrides"));
+
+    for dependencies in sections.filter_map(Value::as_object) {
+        for (name, value) in dependencies {
+            if let (true, Some(version)) = (
+                is_rw_dependency(name, value, workspace_packages),
+                value.as_str(),
+            ) {
+                rw_versions
+                    .entry(version.to_owned())
+                    .or_default()
+                    .insert(path.to_string_lossy().into_owned());
+            }
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use semver_rs::satisfies;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use crate::confirm;
+use crate::diagnostics;
+use crate::exec::{display_command, exec_in};
+use crate::i18n::t;
+use crate::package_json;
+use crate::theme;
+use crate::yarns::{self, Origin};
+
+/// The package managers the generated project can be installed with
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageManager {
+    Yarn,
+    Pnpm,
+    Npm,
+    Bun,
+}
+
+impl PackageManager {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PackageManager::Yarn => "yarn",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Npm => "npm",
+            PackageManager::Bun => "bun",
+        }
+    }
+
+    /// Reads the package manager from the `packageManager` field in the
+    /// project's root package.json. Defaults to yarn, which is what the
+    /// fixture uses
+    pub fn from_project(project_dir: &str) -> PackageManager {
+        let path = Path::new(project_dir).join("package.json");
+        let contents = fs::read_to_string(path).expect("Failed to read file");
+        let json: Value = serde_json::from_str(&contents).expect("Failed to parse json");
+
+        let name = json
+            .get("packageManager")
+            .and_then(|package_manager| package_manager.as_str())
+            .and_then(|spec| spec.split('@').next())
+            .unwrap_or("yarn");
+
+        match name {
+            "pnpm" => PackageManager::Pnpm,
+            "npm" => PackageManager::Npm,
+            "bun" => PackageManager::Bun,
+            _ => PackageManager::Yarn,
+        }
+    }
+
+    pub fn install_command(&self) -> [&'static str; 2] {
+        [self.name(), "install"]
+    }
+
+    /// The command prefix used to run a binary from the project's
+    /// dependencies, like `rw`
+    pub fn exec_prefix(&self) -> &'static [&'static str] {
+        match self {
+            PackageManager::Yarn => &["yarn"],
+            PackageManager::Pnpm => &["pnpm"],
+            PackageManager::Npm => &["npx"],
+            PackageManager::Bun => &["bun", "run"],
+        }
+    }
+
+    /// The oldest version of the package manager we know works with the
+    /// RSC kitchen sink fixture
+    fn min_version(&self) -> &'static str {
+        match self {
+            PackageManager::Yarn => ">=4",
+            PackageManager::Pnpm => ">=9",
+            PackageManager::Npm => ">=10",
+            PackageManager::Bun => ">=1.1",
+        }
+    }
+
+    /// Makes sure the package manager is available in PATH
+    pub fn check_installation(&self) {
+        if *self == PackageManager::Yarn {
+            check_yarn_installation();
+            return;
+        }
+
+        match which::which(self.name()) {
+            Ok(path) => {
+                tracing::debug!("{} path: {}", self.name(), path.to_string_lossy());
+            }
+            Err(_) => {
+                eprintln!(
+                    "{}",
+                    theme::error(t!("package_manager.not_found", name = self.name()))
+                );
+                eprintln!("{}", t!("package_manager.not_found_hint"));
+                diagnostics::fail();
+            }
+        }
+    }
+
+    /// Checks the package manager version when run inside the project and
+    /// returns it.
+    ///
+    /// For yarn, which should have been switched to the exact version in the
+    /// project's `packageManager` field by corepack, a mismatch can be fixed
+    /// by running `corepack use`. That's done without asking if `fix` is true
+    pub fn check_version(&self, installation_dir: &str, fix: bool) -> String {
+        let output = exec_in(&[self.name(), "--version"], installation_dir);
+        let version = output.trim().to_owned();
+
+        tracing::debug!("{} version: {version}", self.name());
+
+        if *self == PackageManager::Yarn {
+            if let Some(spec) = read_package_manager_spec(installation_dir) {
+                return check_exact_yarn_version(installation_dir, &version, &spec, fix);
+            }
+        }
+
+        if satisfies(&version, self.min_version(), None).unwrap() {
+            return version;
+        }
+
+        if *self == PackageManager::Yarn {
+            eprintln!("{}", t!("package_manager.yarn_broken"));
+        } else {
+            eprintln!(
+                "{}",
+                t!(
+                    "package_manager.too_old",
+                    name = self.name(),
+                    version = self.min_version().trim_start_matches(">=")
+                )
+            );
+        }
+
+        diagnostics::fail();
+    }
+
+    /// Points the project's root package.json at this package manager.
+    ///
+    /// The fixture is set up for yarn, so for yarn this is a no-op. For the
+    /// other package managers `packageManager` is rewritten to the version
+    /// that's installed, and pnpm additionally gets a `pnpm-workspace.yaml`,
+    /// because it doesn't read the `workspaces` field
+    pub fn configure_project(&self, installation_dir: &str, version: &str) {
+        if *self == PackageManager::Yarn {
+            return;
+        }
+
+        let path = Path::new(installation_dir).join("package.json");
+        let (mut json, style) = package_json::read(&path);
+
+        tracing::debug!(
+            "Setting packageManager in {} to {}@{version}",
+            path.to_string_lossy(),
+            self.name()
+        );
+
+        json["packageManager"] = Value::String(format!("{}@{version}", self.name()));
+
+        package_json::write(&path, &json, &style);
+
+        if *self == PackageManager::Pnpm {
+            let workspaces = json
+                .get("workspaces")
+                .and_then(|workspaces| workspaces.as_array())
+                .cloned()
+                .unwrap_or_default();
+
+            let mut yaml = String::from("packages:\n");
+            for workspace in workspaces.iter().filter_map(|w| w.as_str()) {
+                yaml.push_str(&format!("  - '{workspace}'\n"));
+            }
+
+            fs::write(
+                Path::new(installation_dir).join("pnpm-workspace.yaml"),
+                yaml,
+            )
+            .expect("Failed to write file");
+        }
+    }
+}
+
+/// Why the yarn installation isn't usable
+#[derive(Debug)]
+pub enum YarnProblem {
+    NotFound,
+    /// A corepack yarn is installed, but another yarn, from here, comes
+    /// before it in PATH
+    CorepackShadowed(Origin),
+    /// Multiple yarn binaries, none of them from corepack
+    Multiple(usize),
+}
+
+impl YarnProblem {
+    pub fn print_guidance(&self) {
+        match self {
+            YarnProblem::NotFound => eprintln!("{}", theme::error(t!("yarn.not_found"))),
+            YarnProblem::CorepackShadowed(_) => {
+                eprintln!("{}", theme::error(t!("yarn.shadowed")))
+            }
+            YarnProblem::Multiple(_) => eprintln!("{}", theme::warning(t!("yarn.multiple"))),
+        }
+
+        eprintln!("{}", self.hint());
+
+        if matches!(
+            self,
+            YarnProblem::CorepackShadowed(_) | YarnProblem::Multiple(_)
+        ) {
+            eprintln!("{}", yarns::describe_all());
+        }
+    }
+
+    /// How to fix the problem, tailored to where the offending yarn is from
+    pub fn hint(&self) -> String {
+        match self {
+            YarnProblem::NotFound => t!("yarn.not_found_hint"),
+            YarnProblem::CorepackShadowed(origin) => t!(
+                "yarn.shadowed_hint",
+                origin = origin.name(),
+                guidance = origin.guidance().unwrap_or_default()
+            ),
+            YarnProblem::Multiple(_) => t!("yarn.multiple_hint"),
+        }
+    }
+}
+
+fn check_yarn_installation() {
+    if let Err(problem) = diagnose_yarn_installation() {
+        problem.print_guidance();
+        diagnostics::fail();
+    }
+}
+
+/// Makes sure there's exactly one yarn in PATH, and that it's the one
+/// installed by corepack
+pub fn diagnose_yarn_installation() -> Result<(), YarnProblem> {
+    let yarns = yarns::find_all();
+
+    for yarn in &yarns {
+        tracing::debug!(
+            "Found yarn: {} ({})",
+            yarn.path.to_string_lossy(),
+            yarn.origin.name()
+        );
+    }
+
+    let Some(first) = yarns.first() else {
+        return Err(YarnProblem::NotFound);
+    };
+
+    if first.origin == Origin::Corepack {
+        return Ok(());
+    }
+
+    if yarns.iter().any(|yarn| yarn.origin == Origin::Corepack) {
+        return Err(YarnProblem::CorepackShadowed(first.origin.clone()));
+    }
+
+    if yarns.len() > 1 {
+        return Err(YarnProblem::Multiple(yarns.len()));
+    }
+
+    Ok(())
+}
+
+/// Reads the `packageManager` field, like `yarn@4.1.1+sha512.abc...`, from the
+/// project's root package.json
+fn read_package_manager_spec(project_dir: &str) -> Option<String> {
+    let path = Path::new(project_dir).join("package.json");
+    let contents = fs::read_to_string(path).ok()?;
+    let json: Value = serde_json::from_str(&contents).ok()?;
+
+    json.get("packageManager")?.as_str().map(str::to_owned)
+}
+
+/// The version part of a `packageManager` spec, without any hash
+fn spec_version(spec: &str) -> &str {
+    let version = spec.split_once('@').map_or("", |(_, version)| version);
+    version.split('+').next().unwrap_or(version)
+}
+
+fn check_exact_yarn_version(project_dir: &str, version: &str, spec: &str, fix: bool) -> String {
+    let required = spec_version(spec);
+
+    if version == required {
+        return version.to_owned();
+    }
+
+    eprintln!(
+        "{}",
+        theme::error(t!("yarn.wrong_version", required = required))
+    );
+    eprintln!("{}", t!("yarn.wrong_version_got", version = version));
+
+    let corepack_use = ["corepack", "use", spec];
+
+    if !fix
+        && !confirm(&t!(
+            "yarn.corepack_use_question",
+            command = display_command(&corepack_use)
+        ))
+    {
+        eprintln!("{}", t!("yarn.corepack_use_hint"));
+        diagnostics::fail();
+    }
+
+    exec_in(&corepack_use, project_dir);
+
+    let output = exec_in(&["yarn", "--version"], project_dir);
+    let version = output.trim();
+
+    if version != required {
+        eprintln!(
+            "{}",
+            theme::error(t!("yarn.still_wrong_version", version = version))
+        );
+        eprintln!("{}", t!("yarn.still_wrong_version_hint"));
+        diagnostics::fail();
+    }
+
+    version.to_owned()
+}
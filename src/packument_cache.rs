@@ -0,0 +1,85 @@
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::fs_util;
+
+/// How long a cached packument is used without asking the registry
+const TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A packument from an earlier run
+pub struct Entry {
+    pub packument: Value,
+    pub etag: Option<String>,
+    fetched_at: u64,
+}
+
+impl Entry {
+    /// Whether it's recent enough to use as it is. Older entries have to be
+    /// revalidated with the registry, using their etag
+    pub fn is_fresh(&self) -> bool {
+        now().saturating_sub(self.fetched_at) < TTL.as_secs()
+    }
+}
+
+/// The cached packument for `url`, if there is one
+pub fn read(url: &str) -> Option<Entry> {
+    let path = path(url)?;
+    let contents = fs::read_to_string(&path).ok()?;
+    let json: Value = serde_json::from_str(&contents).ok()?;
+
+    // Guard against hash collisions
+    if json.get("url").and_then(Value::as_str) != Some(url) {
+        return None;
+    }
+
+    tracing::debug!("Found a cached packument in {}", path.to_string_lossy());
+
+    Some(Entry {
+        packument: json.get("packument")?.clone(),
+        etag: json.get("etag").and_then(Value::as_str).map(str::to_owned),
+        fetched_at: json.get("fetched_at").and_then(Value::as_u64)?,
+    })
+}
+
+/// Caches the packument for `url`. Failing to do so isn't an error, it just
+/// means the next run fetches it again
+pub fn write(url: &str, etag: Option<&str>, packument: &Value) {
+    let Some(path) = path(url) else {
+        return;
+    };
+
+    let json = json!({
+        "url": url,
+        "etag": etag,
+        "fetched_at": now(),
+        "packument": packument,
+    });
+
+    // Written to a temp file first and renamed into place, so that runs
+    // reading the entry at the same time never see half of it
+    let temp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    let result = fs::create_dir_all(path.parent().unwrap())
+        .and_then(|()| fs::write(&temp_path, json.to_string()))
+        .and_then(|()| fs::rename(&temp_path, &path));
+
+    if let Err(e) = result {
+        tracing::debug!("Could not cache {url} in {}: {e}", path.to_string_lossy());
+    }
+}
+
+/// `<cache dir>/packuments/<hash of the url>.json`. The url includes the
+/// registry, so each registry gets its own entries
+fn path(url: &str) -> Option<PathBuf> {
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+
+    fs_util::cache_dir().map(|dir| dir.join("packuments").join(format!("{hash}.json")))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
@@ -0,0 +1,242 @@
+use reqwest::header::{
+    HeaderMap, HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_RANGE, RANGE,
+};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::events;
+use crate::fs_util;
+use crate::http::FetchError;
+use crate::i18n::t;
+use crate::lock::{self, FileLock};
+
+/// A download that's written to the cache dir as it comes in, so that a
+/// retry, or the next run, can pick up where it stopped with a `Range`
+/// request instead of starting over.
+///
+/// Resuming needs the server to have sent a strong `ETag` and the total
+/// size. The `ETag` goes in an `If-Range` header, so a server with a newer
+/// archive answers with all of it instead, and so does a server that
+/// doesn't support ranges. Either way the download starts over
+pub struct PartialDownload {
+    url: String,
+    /// `None` if there's nowhere to keep the download, which just means it
+    /// can't be resumed
+    paths: Option<Paths>,
+    /// What's already been downloaded, and the `ETag` it was served with
+    downloaded: u64,
+    etag: Option<String>,
+    total: Option<u64>,
+    /// Where new bytes go. Only open once the response has been accepted
+    file: Option<File>,
+    /// Keeps other runs downloading the same url from writing to the same
+    /// files
+    _lock: Option<FileLock>,
+}
+
+struct Paths {
+    data: PathBuf,
+    meta: PathBuf,
+    lock: PathBuf,
+}
+
+impl PartialDownload {
+    /// Picks up whatever an earlier attempt left of `url`. If another run is
+    /// downloading it right now, this download isn't kept at all
+    pub fn open(url: &str) -> PartialDownload {
+        let mut paths = paths(url);
+        let lock = paths
+            .as_ref()
+            .and_then(|paths| lock::try_lock(&paths.lock).ok().flatten());
+
+        if paths.is_some() && lock.is_none() {
+            tracing::debug!("Another run is downloading {url}, so this one can't be resumed");
+            paths = None;
+        }
+
+        let mut download = PartialDownload {
+            url: url.to_owned(),
+            paths,
+            downloaded: 0,
+            etag: None,
+            total: None,
+            file: None,
+            _lock: lock,
+        };
+
+        let Some(paths) = &download.paths else {
+            return download;
+        };
+
+        let meta: Option<Value> = fs::read_to_string(&paths.meta)
+            .ok()
+            .and_then(|meta| serde_json::from_str(&meta).ok());
+        let Some(meta) = meta.filter(|meta| meta.get("url").and_then(Value::as_str) == Some(url))
+        else {
+            return download;
+        };
+
+        download.etag = meta.get("etag").and_then(Value::as_str).map(str::to_owned);
+        download.total = meta.get("total").and_then(Value::as_u64);
+        download.downloaded = fs::metadata(&paths.data).map_or(0, |data| data.len());
+
+        download
+    }
+
+    /// The headers that ask for the rest of the download. Empty if there's
+    /// nothing to resume
+    pub fn range_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        let (Some(etag), Some(total)) = (&self.etag, self.total) else {
+            return headers;
+        };
+
+        if self.downloaded == 0 || self.downloaded >= total {
+            return headers;
+        }
+
+        if let Ok(etag) = HeaderValue::from_str(etag) {
+            tracing::debug!(
+                "Resuming the download of {} at {} of {total} bytes",
+                self.url,
+                self.downloaded
+            );
+
+            headers.insert(
+                RANGE,
+                HeaderValue::from_str(&format!("bytes={}-", self.downloaded)).unwrap(),
+            );
+            headers.insert(IF_RANGE, etag);
+        }
+
+        headers
+    }
+
+    /// Decides, from the response's status and headers, whether the
+    /// download continues where it stopped or starts over
+    pub fn begin(&mut self, status: StatusCode, headers: &HeaderMap) -> Result<(), FetchError> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+        };
+
+        if status == StatusCode::PARTIAL_CONTENT {
+            // `bytes <first>-<last>/<total>`
+            let first = header(CONTENT_RANGE)
+                .and_then(|range| range.strip_prefix("bytes "))
+                .and_then(|range| range.split(['-', '/']).next())
+                .and_then(|first| first.parse::<u64>().ok());
+
+            if first != Some(self.downloaded) {
+                self.discard();
+                return Err(FetchError::Retryable(t!(
+                    "download.wrong_range",
+                    url = self.url
+                )));
+            }
+
+            self.file = self
+                .paths
+                .as_ref()
+                .and_then(|paths| OpenOptions::new().append(true).open(&paths.data).ok());
+
+            events::status(t!(
+                "download.resuming",
+                megabytes = format!("{:.1}", self.downloaded as f64 / 1_000_000.0)
+            ));
+
+            return Ok(());
+        }
+
+        // Strong validators only. Weak ones can't be used with `If-Range`
+        self.etag = header(ETAG)
+            .filter(|etag| !etag.starts_with("W/"))
+            .map(str::to_owned);
+        self.total = header(CONTENT_LENGTH).and_then(|length| length.parse().ok());
+        self.downloaded = 0;
+        self.file = match self.start_over() {
+            Ok(file) => Some(file),
+            Err(e) => {
+                tracing::debug!("Can't keep the download of {} for resuming: {e}", self.url);
+                None
+            }
+        };
+
+        Ok(())
+    }
+
+    /// The size of the whole download, if the server told us
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// What was downloaded before this attempt, to be read before the rest
+    /// of the response. `None` if the download started over
+    pub fn existing(&self) -> io::Result<Option<File>> {
+        match &self.paths {
+            Some(paths) if self.downloaded > 0 => File::open(&paths.data).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Keeps `bytes` for resuming. Failing to do so isn't an error, the
+    /// download just can't be resumed
+    pub fn append(&mut self, bytes: &[u8]) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+
+        if let Err(e) = file.write_all(bytes) {
+            tracing::debug!("Can't keep the download of {} for resuming: {e}", self.url);
+            self.discard();
+        }
+    }
+
+    /// Forgets the download. For when it's complete, or turned out to be
+    /// corrupted and has to start over
+    pub fn discard(&mut self) {
+        self.file = None;
+
+        if let Some(paths) = &self.paths {
+            fs::remove_file(&paths.data).ok();
+            fs::remove_file(&paths.meta).ok();
+        }
+    }
+
+    /// Empties the data file and records what the new download is
+    fn start_over(&self) -> io::Result<File> {
+        let Some(paths) = &self.paths else {
+            return Err(io::Error::other("No cache dir"));
+        };
+
+        fs::create_dir_all(paths.data.parent().unwrap())?;
+
+        let meta = json!({
+            "url": self.url,
+            "etag": self.etag,
+            "total": self.total,
+        });
+        fs::write(&paths.meta, meta.to_string())?;
+
+        File::create(&paths.data)
+    }
+}
+
+/// `<cache dir>/downloads/<hash of the url>`, with the response's `ETag`
+/// and size in a `.json` file next to it, and a `.lock` file
+fn paths(url: &str) -> Option<Paths> {
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    let dir = fs_util::cache_dir()?.join("downloads");
+
+    Some(Paths {
+        data: dir.join(&hash),
+        meta: dir.join(format!("{hash}.json")),
+        lock: dir.join(format!("{hash}.lock")),
+    })
+}
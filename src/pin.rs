@@ -0,0 +1,67 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::i18n::t;
+use crate::package_json::{find_package_jsons, print_changes, update_package_jsons};
+use crate::registry::get_tagged_version;
+use crate::{diagnostics, theme, Config};
+
+/// Sets every @redwoodjs/* dependency in the package.json files under `dir`
+/// to `version`, or to the latest version of `tag`, and prints what changed
+/// like a diff. Nothing is downloaded or installed, so it works on any tree
+/// of packages, like the framework's test projects.
+///
+/// A given `version` is used as it is, without asking the registry, like
+/// `--canary-version`
+pub fn run(dir: &str, version: Option<&str>, tag: &str) {
+    if !Path::new(dir).is_dir() {
+        eprintln!("{}", theme::error(t!("pin.not_a_directory", dir = dir)));
+        diagnostics::fail();
+    }
+
+    let package_jsons: Vec<_> = find_package_jsons(dir).collect();
+
+    if package_jsons.is_empty() {
+        eprintln!("{}", theme::error(t!("pin.no_package_jsons", dir = dir)));
+        diagnostics::fail();
+    }
+
+    let check_registry = version.is_none();
+    let version = version
+        .map(str::to_owned)
+        .unwrap_or_else(|| get_tagged_version("@redwoodjs/core", tag));
+
+    let changes = update_package_jsons(package_jsons.into_iter(), version.clone(), check_registry);
+
+    print_changes(Path::new(dir), &changes);
+
+    let files = changes
+        .iter()
+        .map(|change| &change.path)
+        .collect::<BTreeSet<_>>()
+        .len();
+
+    if changes.is_empty() {
+        println!("{}", t!("pin.already_pinned", version = version));
+    } else if Config::is_dry_run() {
+        println!(
+            "{}",
+            t!(
+                "dry_run.pin",
+                count = changes.len(),
+                files = files,
+                version = version
+            )
+        );
+    } else {
+        println!(
+            "{}",
+            t!(
+                "pin.pinned",
+                count = changes.len(),
+                files = files,
+                version = version
+            )
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::events;
+use crate::exec::exec_streaming_in_with_env;
+use crate::i18n::t;
+use crate::package_manager::PackageManager;
+use crate::{theme, Config};
+
+/// Plugins are executables in PATH named `rw-quickstart-plugin-<name>`, like
+/// git's and cargo's external subcommands
+const PREFIX: &str = "rw-quickstart-plugin-";
+
+/// Finds the executables of the `--plugin`s. Exits, listing the plugins
+/// that are installed, if any of them can't be found. Runs before anything
+/// is created, so a typo doesn't cost a download
+pub fn resolve(names: &[String]) -> Vec<(String, PathBuf)> {
+    let (found, missing): (Vec<_>, Vec<_>) = names
+        .iter()
+        .map(|name| (name, which::which(format!("{PREFIX}{name}")).ok()))
+        .partition(|(_, path)| path.is_some());
+
+    if !missing.is_empty() {
+        for (name, _) in missing {
+            eprintln!(
+                "{}",
+                theme::error(t!("plugins.not_found", name = name, prefix = PREFIX))
+            );
+        }
+
+        let installed = discover();
+        if !installed.is_empty() {
+            eprintln!(
+                "{}",
+                t!(
+                    "plugins.installed",
+                    plugins = installed.into_keys().collect::<Vec<_>>().join(", ")
+                )
+            );
+        }
+
+        std::process::exit(1);
+    }
+
+    found
+        .into_iter()
+        .map(|(name, path)| (name.clone(), path.unwrap()))
+        .collect()
+}
+
+/// Runs the plugins in the project, in order, after the install and before
+/// the post hooks. Each one is its own phase, and its output is shown like
+/// the install's.
+///
+/// A plugin gets the project's directory as its working directory, and
+/// what it needs to know about the run in env vars:
+///
+/// - `RW_QUICKSTART_PROJECT_DIR`: the absolute path of the project
+/// - `RW_QUICKSTART_PACKAGE_MANAGER`: `yarn`, `npm`, `pnpm` or `bun`
+/// - `RW_QUICKSTART_EXEC`: how to run the project's binaries, like `yarn`
+///   for `yarn rw g page`
+///
+/// A plugin that fails stops everything, so nothing is committed
+pub fn run(plugins: &[(String, PathBuf)], package_manager: PackageManager, project_dir: &Path) {
+    let absolute_dir = std::path::absolute(project_dir).unwrap_or(project_dir.to_path_buf());
+    let env = [
+        (
+            "RW_QUICKSTART_PROJECT_DIR".to_owned(),
+            absolute_dir.to_string_lossy().into_owned(),
+        ),
+        (
+            "RW_QUICKSTART_PACKAGE_MANAGER".to_owned(),
+            package_manager.name().to_owned(),
+        ),
+        (
+            "RW_QUICKSTART_EXEC".to_owned(),
+            package_manager.exec_prefix().join(" "),
+        ),
+    ];
+
+    for (name, path) in plugins {
+        let phase = events::start("plugin");
+        let path = path.to_string_lossy();
+
+        if Config::is_dry_run() {
+            events::status(t!("dry_run.plugin", name = name, path = path));
+        } else {
+            events::status(t!("progress.plugin", name = name));
+            exec_streaming_in_with_env(&[path.as_ref()], project_dir, &env);
+        }
+
+        phase.finish(json!({ "plugin": name, "path": path }));
+    }
+}
+
+/// Prints the installed plugins, for the `plugins` subcommand
+pub fn list() {
+    let plugins = discover();
+
+    if plugins.is_empty() {
+        println!("{}", t!("plugins.none", prefix = PREFIX));
+        return;
+    }
+
+    for (name, path) in plugins {
+        println!("{name}\t{}", path.to_string_lossy());
+    }
+}
+
+/// Every plugin in PATH, by name. When there are several with the same name,
+/// the first one in PATH wins, just like when it's run
+fn discover() -> BTreeMap<String, PathBuf> {
+    let mut plugins = BTreeMap::new();
+    let path = std::env::var_os("PATH").unwrap_or_default();
+
+    for dir in std::env::split_paths(&path) {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let file_name = if cfg!(windows) {
+                path.file_stem()
+            } else {
+                path.file_name()
+            };
+
+            let Some(name) = file_name
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(PREFIX))
+            else {
+                continue;
+            };
+
+            if !name.is_empty() && is_executable(&path) {
+                plugins.entry(name.to_owned()).or_insert(path);
+            }
+        }
+    }
+
+    plugins
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(windows)]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -0,0 +1,221 @@
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics;
+use crate::i18n::t;
+use crate::theme;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Room needed in the temp dir for the extracted Redwood repo
+const TEMP_SPACE_NEEDED: u64 = 500 * MIB;
+/// Room needed for the project itself, without its dependencies
+const PROJECT_SPACE_NEEDED: u64 = 50 * MIB;
+/// Room needed for `node_modules` after installing
+const INSTALL_SPACE_NEEDED: u64 = 1536 * MIB;
+
+/// A directory without enough free space
+#[derive(Debug)]
+pub struct Shortfall {
+    pub dir: PathBuf,
+    pub needed: u64,
+    pub available: u64,
+}
+
+impl Shortfall {
+    /// How much is needed, and how much there is
+    pub fn describe(&self) -> String {
+        t!(
+            "preflight.space_needed",
+            needed = self.needed / MIB,
+            dir = self.dir.to_string_lossy(),
+            available = self.available / MIB
+        )
+    }
+}
+
+/// What to do about a `Shortfall`
+pub fn space_hint() -> String {
+    t!("preflight.space_hint")
+}
+
+/// Makes sure there's enough free space for the extraction and the install,
+/// and that the project can be written, before anything is downloaded.
+///
+/// `installation_dir` doesn't have to exist yet. Its closest existing
+/// ancestor is checked instead
+pub fn check(installation_dir: &Path, install: bool) {
+    check_writable(&existing_ancestor(installation_dir));
+
+    if let Some(shortfall) = find_shortfalls(installation_dir, install).first() {
+        eprintln!(
+            "{}",
+            theme::error(t!(
+                "preflight.no_space",
+                dir = shortfall.dir.to_string_lossy()
+            ))
+        );
+        eprintln!("{}", shortfall.describe());
+        eprintln!("{}", space_hint());
+        diagnostics::fail();
+    }
+}
+
+/// The directories that don't have enough free space for creating a project
+/// in `installation_dir`
+pub fn find_shortfalls(installation_dir: &Path, install: bool) -> Vec<Shortfall> {
+    let temp_dir = std::env::temp_dir();
+    let target_dir = existing_ancestor(installation_dir);
+
+    let mut target_needed = PROJECT_SPACE_NEEDED;
+    if install {
+        target_needed += INSTALL_SPACE_NEEDED;
+    }
+
+    if same_filesystem(&temp_dir, &target_dir) {
+        check_space(&target_dir, TEMP_SPACE_NEEDED + target_needed)
+            .into_iter()
+            .collect()
+    } else {
+        check_space(&temp_dir, TEMP_SPACE_NEEDED)
+            .into_iter()
+            .chain(check_space(&target_dir, target_needed))
+            .collect()
+    }
+}
+
+/// Makes sure the project isn't created inside another Node project that
+/// yarn would treat as its parent. Exits unless `allow_nested`, in which case
+/// it only warns
+pub fn check_nesting(installation_dir: &Path, allow_nested: bool) {
+    let Some((parent, reason)) = find_enclosing_project(installation_dir) else {
+        return;
+    };
+
+    let message = t!(
+        "preflight.nested",
+        dir = installation_dir.to_string_lossy(),
+        parent = parent.to_string_lossy(),
+        reason = reason
+    );
+
+    if allow_nested {
+        eprintln!("{}", theme::warning(message));
+    } else {
+        eprintln!("{}", theme::error(message));
+    }
+
+    eprintln!("{}", t!("preflight.nested_explanation"));
+
+    if allow_nested {
+        eprintln!("{}", t!("preflight.nested_allowed_hint"));
+    } else {
+        eprintln!("{}", t!("preflight.nested_hint"));
+        diagnostics::fail();
+    }
+}
+
+/// The closest ancestor that's a yarn project or a workspace root, and what
+/// makes it one
+fn find_enclosing_project(installation_dir: &Path) -> Option<(PathBuf, String)> {
+    let installation_dir = std::path::absolute(installation_dir).ok()?;
+    // ~/.yarnrc.yml is yarn's user-wide config, not a project
+    let home = dirs::home_dir();
+
+    installation_dir.ancestors().skip(1).find_map(|dir| {
+        if has_workspaces(&dir.join("package.json")) {
+            Some((dir.to_path_buf(), t!("preflight.has_workspaces")))
+        } else if dir.join(".yarnrc.yml").is_file() && home.as_deref() != Some(dir) {
+            Some((dir.to_path_buf(), t!("preflight.has_yarnrc")))
+        } else {
+            None
+        }
+    })
+}
+
+fn has_workspaces(package_json: &Path) -> bool {
+    fs::read_to_string(package_json)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .is_some_and(|json| json.get("workspaces").is_some())
+}
+
+fn existing_ancestor(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).expect("Failed to resolve the installation dir");
+
+    absolute
+        .ancestors()
+        .find(|dir| dir.exists())
+        .unwrap_or(&absolute)
+        .to_path_buf()
+}
+
+fn check_space(dir: &Path, needed: u64) -> Option<Shortfall> {
+    // Some filesystems, like network mounts, can't report their free space.
+    // Let the user try anyway
+    let available = match fs4::available_space(dir) {
+        Ok(available) => available,
+        Err(e) => {
+            tracing::debug!(
+                "Could not check free space in {}: {e}",
+                dir.to_string_lossy()
+            );
+            return None;
+        }
+    };
+
+    tracing::debug!(
+        "{} MiB available in {}, {} MiB needed",
+        available / MIB,
+        dir.to_string_lossy(),
+        needed / MIB
+    );
+
+    (available < needed).then(|| Shortfall {
+        dir: dir.to_path_buf(),
+        needed,
+        available,
+    })
+}
+
+fn check_writable(dir: &Path) {
+    match tempfile::tempfile_in(dir) {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            eprintln!(
+                "{}",
+                theme::error(t!("preflight.not_writable", dir = dir.to_string_lossy()))
+            );
+            eprintln!("{}", t!("preflight.not_writable_hint"));
+            diagnostics::fail();
+        }
+        Err(e) => {
+            eprintln!(
+                "{}",
+                theme::error(t!(
+                    "preflight.cant_write",
+                    dir = dir.to_string_lossy(),
+                    error = e
+                ))
+            );
+            diagnostics::fail();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    a.components().next() == b.components().next()
+}
@@ -0,0 +1,106 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::i18n::t;
+use crate::package_json;
+
+/// Written into the project's root, recording what it was created from
+pub const FILE_NAME: &str = ".rw-quickstart.json";
+
+/// What a project was created from
+#[derive(Debug)]
+pub struct Provenance {
+    /// Where the template came from, like `redwoodjs/redwood@main`
+    pub source: String,
+    /// The commit of the source the template was taken from, if known
+    pub commit: Option<String>,
+    pub template: String,
+    /// The dist-tag the Redwood version was resolved from. `None` if the
+    /// fixture's versions were kept
+    pub redwood_tag: Option<String>,
+    pub redwood_version: Option<String>,
+    /// What was in the project's directory before the project was put in
+    /// it. `None` if we created the directory
+    pub existing_files: Option<Vec<String>>,
+}
+
+/// Writes `provenance` to the project, along with our version and the time
+pub fn write(project_dir: &Path, provenance: &Provenance) {
+    let json = json!({
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "source": provenance.source,
+        "commit": provenance.commit,
+        "template": provenance.template,
+        "redwood_tag": provenance.redwood_tag,
+        "redwood_version": provenance.redwood_version,
+        "existing_files": provenance.existing_files,
+        "created_at": now(),
+    });
+
+    write_json(project_dir, &json);
+}
+
+/// Reads the provenance file, if the project has one
+pub fn read(project_dir: &Path) -> Option<Value> {
+    let contents = fs::read_to_string(project_dir.join(FILE_NAME)).ok()?;
+
+    serde_json::from_str(&contents)
+        .inspect_err(|e| {
+            tracing::warn!("{}", t!("provenance.invalid", file = FILE_NAME, error = e));
+        })
+        .ok()
+}
+
+/// Records that the project has been upgraded to `redwood_version`, keeping
+/// what it was originally created from
+pub fn record_upgrade(project_dir: &Path, redwood_version: &str) {
+    let Some(mut json) = read(project_dir) else {
+        return;
+    };
+
+    json["redwood_version"] = Value::String(redwood_version.to_owned());
+    json["upgraded_at"] = Value::String(now());
+
+    write_json(project_dir, &json);
+}
+
+/// A one line description of what the project was created from, like
+/// `test-project-rsc-kitchen-sink from redwoodjs/redwood@main (abc1234)`
+pub fn describe(json: &Value) -> String {
+    let unknown = t!("provenance.unknown");
+    let field = |name: &str| {
+        json.get(name)
+            .and_then(Value::as_str)
+            .unwrap_or(&unknown)
+            .to_owned()
+    };
+
+    let mut description = t!(
+        "provenance.describe",
+        template = field("template"),
+        source = field("source")
+    );
+
+    if let Some(commit) = json.get("commit").and_then(Value::as_str) {
+        description.push_str(&format!(" ({})", &commit[..commit.len().min(7)]));
+    }
+
+    description
+}
+
+fn write_json(project_dir: &Path, json: &Value) {
+    let path = project_dir.join(FILE_NAME);
+    tracing::debug!("Writing {}", path.to_string_lossy());
+
+    package_json::write(&path, json, &package_json::JsonStyle::default());
+}
+
+/// The current time, in RFC 3339
+pub fn now() -> String {
+    OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .expect("Failed to format the current time")
+}
@@ -0,0 +1,242 @@
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::diagnostics;
+use crate::http;
+use crate::i18n::t;
+use crate::packument_cache;
+use crate::theme;
+
+pub const DEFAULT_REGISTRY_URL: &str = "https://registry.npmjs.org/";
+/// Asks for the abbreviated packument, falling back to the full one for
+/// registries that don't support it
+const ABBREVIATED_PACKUMENT: &str =
+    "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*";
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+/// Packuments that have already been fetched, by package name
+static PACKUMENTS: Mutex<BTreeMap<String, Value>> = Mutex::new(BTreeMap::new());
+
+/// The npm registry to resolve package versions from
+#[derive(Debug)]
+pub struct Registry {
+    /// Always ends with a `/`
+    pub url: String,
+    token: Option<String>,
+    /// Ignore cached packuments
+    refresh: bool,
+}
+
+/// Figures out which registry to use, and how to authenticate with it.
+///
+/// `--registry` takes precedence over `@redwoodjs:registry` and `registry` in
+/// `.npmrc`. The auth token is read from `NPM_TOKEN`, or from a matching
+/// `//<registry>/:_authToken` entry in `.npmrc`. Both the user's `~/.npmrc`
+/// and the one in the current directory are read, with the latter taking
+/// precedence.
+///
+/// With `refresh`, packuments are always fetched, even if they're cached
+pub fn configure(registry: Option<&str>, refresh: bool) {
+    let npmrc = read_npmrc();
+
+    let url = registry
+        .map(str::to_owned)
+        .or_else(|| npmrc.get("@redwoodjs:registry").cloned())
+        .or_else(|| npmrc.get("registry").cloned())
+        .unwrap_or_else(|| DEFAULT_REGISTRY_URL.to_owned());
+    let url = format!("{}/", url.trim_end_matches('/'));
+
+    // npm matches auth entries on the url without the protocol
+    let auth_key = format!(
+        "{}:_authToken",
+        url.split_once(':').map_or("", |(_, rest)| rest)
+    );
+
+    let token = std::env::var("NPM_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .or_else(|| npmrc.get(&auth_key).cloned());
+
+    tracing::debug!("Using registry {}", http::redact(&url));
+    if token.is_some() {
+        tracing::debug!("Authenticating with the registry using a token");
+    }
+
+    REGISTRY
+        .set(Registry {
+            url,
+            token,
+            refresh,
+        })
+        .expect("The registry can only be configured once");
+}
+
+/// The configured registry. Uses the public npm registry if `configure`
+/// hasn't been called
+pub fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(|| Registry {
+        url: DEFAULT_REGISTRY_URL.to_owned(),
+        token: None,
+        refresh: false,
+    })
+}
+
+/// Resolves a dist-tag, like `canary` or `latest`, to a version
+pub fn get_tagged_version<S: Into<String>>(package: S, tag: &str) -> String {
+    let package: String = package.into();
+    let packument = get_packument(&package);
+
+    let Some(version) = packument
+        .get("dist-tags")
+        .and_then(|dist_tags| dist_tags.get(tag))
+        .and_then(|version| version.as_str())
+    else {
+        eprintln!(
+            "{}",
+            theme::error(t!("registry.no_dist_tag", package = package, tag = tag))
+        );
+        diagnostics::fail();
+    };
+
+    version.to_owned()
+}
+
+/// Whether `version` of the package has been published
+pub fn has_version(package: &str, version: &str) -> bool {
+    get_packument(package)
+        .get("versions")
+        .and_then(|versions| versions.get(version))
+        .is_some()
+}
+
+/// Fetches the package's metadata. Each package is only fetched once per
+/// run, no matter how many projects are created.
+///
+/// Packuments are cached on disk as well. A cached packument is used as it
+/// is for a few minutes, and after that only if the registry says it hasn't
+/// changed
+fn get_packument(package: &str) -> Value {
+    if let Some(packument) = PACKUMENTS.lock().unwrap().get(package) {
+        return packument.clone();
+    }
+
+    let registry = registry();
+    // Scoped packages need their `/` encoded for some registries
+    let url = registry.url.clone() + &package.replace('/', "%2f");
+
+    let cached = if registry.refresh {
+        None
+    } else {
+        packument_cache::read(&url)
+    };
+
+    if let Some(cached) = cached.as_ref().filter(|cached| cached.is_fresh()) {
+        remember(package, &cached.packument);
+        return cached.packument.clone();
+    }
+
+    let (packument, etag) = http::with_retries(&t!("registry.fetch_packument"), || {
+        // The abbreviated packument only has what's needed to install a
+        // package, which is a lot less than the full one
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static(ABBREVIATED_PACKUMENT));
+        if let Some(token) = &registry.token {
+            let mut value = header_value(&format!("Bearer {token}"))?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        if let Some(etag) = cached.as_ref().and_then(|cached| cached.etag.as_ref()) {
+            headers.insert(IF_NONE_MATCH, header_value(etag)?);
+        }
+
+        let resp = http::fetcher().get(&url, headers)?;
+
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (resp.status, &cached) {
+            tracing::debug!("{package} hasn't changed since it was cached");
+            return Ok((cached.packument.clone(), cached.etag.clone()));
+        }
+
+        let resp = http::check_status(resp)?;
+        let etag = resp
+            .headers
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_owned);
+
+        Ok((resp.json()?, etag))
+    });
+
+    packument_cache::write(&url, etag.as_deref(), &packument);
+
+    remember(package, &packument);
+    packument
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, http::FetchError> {
+    HeaderValue::from_str(value)
+        .map_err(|e| http::FetchError::Permanent(t!("registry.invalid_header", error = e)))
+}
+
+fn remember(package: &str, packument: &Value) {
+    PACKUMENTS
+        .lock()
+        .unwrap()
+        .insert(package.to_owned(), packument.clone());
+}
+
+fn read_npmrc() -> HashMap<String, String> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        paths.push(home.join(".npmrc"));
+    }
+    paths.push(PathBuf::from(".npmrc"));
+
+    let mut values = HashMap::new();
+
+    for path in paths {
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+
+        tracing::debug!("Reading {}", path.to_string_lossy());
+
+        values.extend(parse_npmrc(&contents));
+    }
+
+    values
+}
+
+fn parse_npmrc(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_owned(), expand_env_vars(value.trim())))
+        .collect()
+}
+
+/// Replaces `${VAR}` with the value of the env var, like npm does
+fn expand_env_vars(value: &str) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+
+        result.push_str(&rest[..start]);
+        let name = &rest[start + 2..start + end];
+        result.push_str(&std::env::var(name).unwrap_or_default());
+        rest = &rest[start + end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
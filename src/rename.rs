@@ -0,0 +1,183 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use crate::package_json;
+
+/// Files that can have an HTML `<title>`, relative to the project root
+const TITLE_FILES: [&str; 3] = [
+    "web/src/index.html",
+    "web/src/Document.tsx",
+    "web/src/Document.jsx",
+];
+
+/// Turns a directory name into a valid npm package name: lowercase, with
+/// anything but letters, digits, `-`, `_` and `.` replaced by `-`
+pub fn package_name(name: &str) -> String {
+    let mut package_name = String::new();
+
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+            package_name.push(c);
+        } else if !package_name.ends_with('-') {
+            package_name.push('-');
+        }
+    }
+
+    // npm doesn't allow names starting with `.` or `_`
+    let package_name = package_name
+        .trim_start_matches(['.', '_'])
+        .trim_matches('-');
+
+    if package_name.is_empty() {
+        "redwood-app".to_owned()
+    } else {
+        package_name.to_owned()
+    }
+}
+
+/// Replaces the template's name with the project's. `name` is used as-is for
+/// titles, and as a package name for package.json files
+pub fn rename_project(project_dir: &Path, template: &str, name: &str) {
+    let package_name = package_name(name);
+
+    tracing::debug!("Renaming {template} to {name} (package name {package_name})");
+
+    let package_jsons = glob::glob(&format!(
+        "{}/**/package.json",
+        project_dir.to_string_lossy()
+    ))
+    .expect("Failed to glob");
+
+    for path in package_jsons.flatten() {
+        if path.components().any(|c| c.as_os_str() == "node_modules") {
+            continue;
+        }
+
+        rename_package(&path, template, &package_name);
+    }
+
+    set_redwood_title(&project_dir.join("redwood.toml"), name);
+
+    for file in TITLE_FILES {
+        set_html_title(&project_dir.join(file), name);
+    }
+}
+
+/// Renames the root package to `package_name`, and the workspace packages
+/// that have the template name in theirs
+fn rename_package(path: &Path, template: &str, package_name: &str) {
+    let (mut json, style) = package_json::read(path);
+
+    let Some(old_name) = json.get("name").and_then(Value::as_str) else {
+        return;
+    };
+
+    if !old_name.contains(template) {
+        return;
+    }
+
+    let new_name = old_name.replace(template, package_name);
+    tracing::debug!(
+        "Renaming package {old_name} to {new_name} in {}",
+        path.to_string_lossy()
+    );
+
+    json["name"] = Value::String(new_name);
+    package_json::write(path, &json, &style);
+}
+
+/// Sets `title` in the `[web]` section, keeping the rest of the file as it is
+fn set_redwood_title(path: &Path, title: &str) {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+
+    let mut in_web_section = false;
+    let mut changed = false;
+
+    let lines: Vec<String> = contents
+        .split_inclusive('\n')
+        .map(|line| {
+            let trimmed = line.trim();
+
+            if trimmed.starts_with('[') {
+                in_web_section = trimmed == "[web]";
+            } else if in_web_section && trimmed.starts_with("title") && trimmed.contains('=') {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                let line_ending = if line.ends_with("\r\n") { "\r\n" } else { "\n" };
+                changed = true;
+
+                // Serializing a string gives us a valid, quoted TOML string
+                return format!("{indent}title = {}{line_ending}", toml::Value::from(title));
+            }
+
+            line.to_owned()
+        })
+        .collect();
+
+    if changed {
+        fs::write(path, lines.concat()).expect("Failed to write redwood.toml");
+    }
+}
+
+/// Replaces the text of a literal `<title>`. Dynamic titles, like ones using
+/// a JSX expression, are left alone
+fn set_html_title(path: &Path, title: &str) {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+
+    let Some(start) = contents.find("<title>").map(|i| i + "<title>".len()) else {
+        return;
+    };
+    let Some(end) = contents[start..].find("</title>").map(|i| start + i) else {
+        return;
+    };
+
+    if contents[start..end].contains(['{', '<']) {
+        return;
+    }
+
+    let escaped = title
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+
+    tracing::debug!("Setting the title in {}", path.to_string_lossy());
+
+    let contents = format!("{}{escaped}{}", &contents[..start], &contents[end..]);
+    fs::write(path, contents).expect("Failed to write file");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_name_is_lowercase() {
+        assert_eq!(package_name("My-App"), "my-app");
+        assert_eq!(package_name("my_app.v2"), "my_app.v2");
+    }
+
+    #[test]
+    fn package_name_replaces_invalid_characters() {
+        assert_eq!(package_name("my cool app!"), "my-cool-app");
+        assert_eq!(package_name("  my  app  "), "my-app");
+        assert_eq!(package_name("café"), "caf");
+    }
+
+    #[test]
+    fn package_name_trims_leading_dots_and_underscores() {
+        assert_eq!(package_name(".hidden"), "hidden");
+        assert_eq!(package_name("_private"), "private");
+        assert_eq!(package_name("-app-"), "app");
+    }
+
+    #[test]
+    fn package_name_falls_back_to_redwood_app() {
+        assert_eq!(package_name(""), "redwood-app");
+        assert_eq!(package_name("..."), "redwood-app");
+        assert_eq!(package_name("日本"), "redwood-app");
+    }
+}
@@ -0,0 +1,233 @@
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use crate::i18n::t;
+use crate::package_json;
+use crate::provenance;
+use crate::{events, theme, Config};
+
+/// Written into the project's root
+pub const FILE_NAME: &str = "dependency-report.json";
+/// A report that can be written into the project with `--report`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Report {
+    /// The license of every installed package, and the Redwood versions
+    Licenses,
+}
+
+/// An installed package, by name and version
+type Installed = BTreeMap<(String, String), String>;
+
+/// Writes a snapshot of the licenses of every installed package, and of
+/// the versions of the project's own `@redwoodjs/*` dependencies, to
+/// `dependency-report.json`. Handy as a starting point for a compliance
+/// review.
+///
+/// The packages are read from `node_modules`, so there's nothing to report
+/// for Yarn's Plug'n'Play. The project's own workspace packages are left out
+pub fn write_licenses(project_dir: &Path) {
+    let phase = events::start("report");
+    let path = project_dir.join(FILE_NAME);
+
+    if Config::is_dry_run() {
+        events::status(t!("dry_run.report", file = path.to_string_lossy()));
+        phase.finish(json!({ "report": "licenses" }));
+        return;
+    }
+
+    let node_modules = project_dir.join("node_modules");
+    if !node_modules.is_dir() {
+        eprintln!("{}", theme::warning(t!("report.no_node_modules")));
+        phase.finish(json!({ "report": "licenses", "packages": 0 }));
+        return;
+    }
+
+    // Packages that can't be hoisted end up in the workspaces' node_modules
+    let workspace_packages = package_json::workspace_packages(project_dir);
+    let mut installed = Installed::new();
+    walk(&node_modules, &mut installed);
+    for dir in package_json::workspace_dirs(project_dir) {
+        walk(&dir.join("node_modules"), &mut installed);
+    }
+    installed.retain(|(name, _), _| !workspace_packages.contains(name));
+
+    let mut licenses: BTreeMap<&str, u64> = BTreeMap::new();
+    for license in installed.values() {
+        *licenses.entry(license).or_default() += 1;
+    }
+
+    let packages: Vec<Value> = installed
+        .iter()
+        .map(|((name, version), license)| {
+            json!({ "name": name, "version": version, "license": license })
+        })
+        .collect();
+
+    let report = json!({
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "generated_at": provenance::now(),
+        "redwood": redwood_versions(project_dir, &installed, &workspace_packages),
+        "licenses": licenses,
+        "packages": packages,
+    });
+
+    tracing::debug!("Writing {}", path.to_string_lossy());
+    package_json::write(&path, &report, &package_json::JsonStyle::default());
+
+    events::status(t!(
+        "report.written",
+        count = installed.len(),
+        file = FILE_NAME
+    ));
+
+    if let Some(unknown) = licenses.get(UNKNOWN) {
+        events::status(theme::dim(t!("report.unknown", count = unknown)));
+    }
+
+    phase.finish(json!({
+        "report": "licenses",
+        "packages": installed.len(),
+        "licenses": licenses.len(),
+    }));
+}
+
+const UNKNOWN: &str = "UNKNOWN";
+
+/// Collects the packages in `node_modules`, and in the `node_modules` nested
+/// in them. pnpm keeps the real packages in `node_modules/.pnpm`, and links
+/// to them from everywhere else
+fn walk(node_modules: &Path, installed: &mut Installed) {
+    for entry in fs::read_dir(node_modules).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if name == ".pnpm" {
+            for entry in fs::read_dir(&path).into_iter().flatten().flatten() {
+                walk(&entry.path().join("node_modules"), installed);
+            }
+        } else if name.starts_with('@') {
+            for entry in fs::read_dir(&path).into_iter().flatten().flatten() {
+                add_package(&entry.path(), installed);
+            }
+        } else if !name.starts_with('.') {
+            add_package(&path, installed);
+        }
+    }
+}
+
+fn add_package(dir: &Path, installed: &mut Installed) {
+    let Some(json) = fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+    else {
+        return;
+    };
+
+    let field = |name| json.get(name).and_then(Value::as_str).map(str::to_owned);
+    let (Some(name), Some(version)) = (field("name"), field("version")) else {
+        return;
+    };
+
+    installed.insert((name, version), license(&json));
+
+    // Links, like pnpm's and those to workspace packages, are walked where
+    // they point, if at all. Following them could go round in circles
+    let is_link = fs::symlink_metadata(dir).is_ok_and(|metadata| metadata.is_symlink());
+    if !is_link {
+        walk(&dir.join("node_modules"), installed);
+    }
+}
+
+/// The package's SPDX expression. Old packages have an object, or an array
+/// of them in `licenses`
+fn license(json: &Value) -> String {
+    let type_of = |license: &Value| {
+        license
+            .as_str()
+            .or_else(|| license.get("type").and_then(Value::as_str))
+            .map(str::to_owned)
+    };
+
+    if let Some(license) = json.get("license").and_then(type_of) {
+        return license;
+    }
+
+    let licenses: Vec<String> = json
+        .get("licenses")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(type_of)
+        .collect();
+
+    match licenses.len() {
+        0 => UNKNOWN.to_owned(),
+        1 => licenses[0].clone(),
+        _ => format!("({})", licenses.join(" OR ")),
+    }
+}
+
+/// The installed version of each `@redwoodjs/*` package the project depends
+/// on directly, from its root or one of its workspaces. The range in
+/// package.json if it isn't installed. Workspace packages aren't Redwood's
+fn redwood_versions(
+    project_dir: &Path,
+    installed: &Installed,
+    workspace_packages: &BTreeSet<String>,
+) -> BTreeMap<String, String> {
+    let manifests = [project_dir.join("package.json")].into_iter().chain(
+        package_json::workspace_dirs(project_dir)
+            .into_iter()
+            .map(|dir| dir.join("package.json")),
+    );
+
+    let mut direct: BTreeMap<String, String> = BTreeMap::new();
+    for manifest in manifests {
+        let Some(json) = fs::read_to_string(&manifest)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        else {
+            continue;
+        };
+
+        for section in ["dependencies", "devDependencies"] {
+            let dependencies = json.get(section).and_then(Value::as_object);
+            for (name, range) in dependencies.into_iter().flatten() {
+                let Some(range) = range.as_str() else {
+                    continue;
+                };
+
+                if name.starts_with("@redwoodjs/")
+                    && !range.starts_with("workspace:")
+                    && !workspace_packages.contains(name)
+                {
+                    direct.insert(name.clone(), range.to_owned());
+                }
+            }
+        }
+    }
+
+    let versions_of = |name: &str| -> BTreeSet<&str> {
+        installed
+            .keys()
+            .filter(|(installed_name, _)| installed_name == name)
+            .map(|(_, version)| version.as_str())
+            .collect()
+    };
+
+    direct
+        .into_iter()
+        .map(|(name, range)| {
+            let versions = versions_of(&name);
+            let version = if versions.is_empty() {
+                range
+            } else {
+                versions.into_iter().collect::<Vec<_>>().join(", ")
+            };
+            (name, version)
+        })
+        .collect()
+}
@@ -0,0 +1,138 @@
+use semver_rs::satisfies;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+
+use crate::github;
+use crate::http;
+use crate::i18n::t;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/Tobbe/rw_rsc_quickstart/releases/latest";
+
+/// Replaces the running executable with the latest release from GitHub.
+///
+/// Release assets are expected to be named `rw_rsc_quickstart-<os>-<arch>`
+/// (with `.exe` on Windows), each with a `.sha256` file next to it
+pub fn run(check_only: bool) {
+    let current_version = env!("CARGO_PKG_VERSION");
+
+    let release: Value = http::with_retries(&t!("self_update.check"), || {
+        github::get(RELEASES_URL)?.json()
+    });
+
+    let Some(tag) = release.get("tag_name").and_then(Value::as_str) else {
+        eprintln!("{}", t!("self_update.no_tag"));
+        std::process::exit(1);
+    };
+    let latest_version = tag.trim_start_matches('v');
+
+    tracing::debug!("Current version: {current_version}, latest version: {latest_version}");
+
+    if !satisfies(latest_version, &format!(">{current_version}"), None).unwrap_or(false) {
+        println!(
+            "{}",
+            t!("self_update.up_to_date", version = current_version)
+        );
+        return;
+    }
+
+    if check_only {
+        println!(
+            "{}",
+            t!(
+                "self_update.available",
+                version = latest_version,
+                current = current_version
+            )
+        );
+        println!("{}", t!("self_update.available_hint"));
+        return;
+    }
+
+    let asset_name = format!(
+        "rw_rsc_quickstart-{}-{}{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::env::consts::EXE_SUFFIX
+    );
+
+    let binary_url = asset_url(&release, &asset_name);
+    let checksum_url = asset_url(&release, &format!("{asset_name}.sha256"));
+
+    println!(
+        "{}",
+        t!("self_update.downloading", version = latest_version)
+    );
+
+    let binary = download(&binary_url);
+    let checksum = String::from_utf8(download(&checksum_url)).unwrap_or_default();
+
+    // The checksum file might be in `sha256sum` format, with the file name
+    // after the digest
+    let expected = checksum.split_whitespace().next().unwrap_or_default();
+    let actual = format!("{:x}", Sha256::digest(&binary));
+
+    if !actual.eq_ignore_ascii_case(expected) {
+        eprintln!(
+            "{}",
+            t!(
+                "self_update.checksum_mismatch",
+                asset = asset_name,
+                expected = expected,
+                actual = actual
+            )
+        );
+        eprintln!("{}", t!("self_update.not_updating"));
+        std::process::exit(1);
+    }
+
+    let mut new_exe = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+    new_exe
+        .write_all(&binary)
+        .expect("Failed to write new executable");
+
+    // Takes care of making it executable, and of the rename dance needed to
+    // replace a running executable on Windows
+    self_replace::self_replace(new_exe.path()).unwrap_or_else(|e| {
+        eprintln!("{}", t!("self_update.replace_failed", error = e));
+        std::process::exit(1);
+    });
+
+    fs::remove_file(new_exe.path()).ok();
+
+    println!(
+        "{}",
+        t!(
+            "self_update.updated",
+            from = current_version,
+            to = latest_version
+        )
+    );
+}
+
+fn asset_url(release: &Value, name: &str) -> String {
+    let url = release
+        .get("assets")
+        .and_then(Value::as_array)
+        .and_then(|assets| {
+            assets
+                .iter()
+                .find(|asset| asset.get("name").and_then(Value::as_str) == Some(name))
+        })
+        .and_then(|asset| asset.get("browser_download_url"))
+        .and_then(Value::as_str);
+
+    match url {
+        Some(url) => url.to_owned(),
+        None => {
+            eprintln!("{}", t!("self_update.no_asset", asset = name));
+            eprintln!("{}", t!("self_update.no_asset_hint"));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn download(url: &str) -> Vec<u8> {
+    http::with_retries(&t!("self_update.download"), || Ok(github::get(url)?.body))
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::archive::Format;
+use crate::fs_util;
+use crate::i18n::t;
+use crate::{GITHUB_REPO, REPO_BRANCH};
+
+/// Where to get the template from: the Redwood repo on GitHub, a fork of it,
+/// a local checkout, or an archive of the repo. Templates are in its
+/// `__fixtures__` directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    GitHub {
+        repo: String,
+        branch: String,
+    },
+    Local(PathBuf),
+    /// An archive like the ones GitHub serves, with everything in a single
+    /// top level directory. A path to a file, or a url, like an internal
+    /// mirror's
+    Archive(String),
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Source::GitHub {
+            repo: GITHUB_REPO.to_owned(),
+            branch: REPO_BRANCH.to_owned(),
+        }
+    }
+}
+
+impl Source {
+    /// The url to clone the repo from. `None` for local sources
+    pub fn repo_url(&self) -> Option<String> {
+        match self {
+            Source::GitHub { repo, .. } => Some(format!("https://github.com/{repo}.git")),
+            Source::Local(_) | Source::Archive(_) => None,
+        }
+    }
+
+    /// The url of the branch's archive in `format`, or of the given archive.
+    /// `None` for local checkouts and archive files
+    pub fn archive_url(&self, format: Format) -> Option<String> {
+        match self {
+            Source::GitHub { repo, branch } => Some(format!(
+                "https://github.com/{repo}/archive/refs/heads/{branch}.{}",
+                format.extension()
+            )),
+            Source::Archive(location)
+                if location.starts_with("https://") || location.starts_with("http://") =>
+            {
+                Some(location.clone())
+            }
+            Source::Local(_) | Source::Archive(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::GitHub { repo, branch } => write!(f, "{repo}@{branch}"),
+            Source::Local(path) => write!(f, "{}", path.to_string_lossy()),
+            Source::Archive(location) => write!(f, "{location}"),
+        }
+    }
+}
+
+/// Accepts GitHub urls, like `https://github.com/owner/redwood`, with an
+/// optional `/tree/<branch>` or `#<branch>` for branches other than `main`,
+/// and paths to local directories
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let github_path = [
+            "https://github.com/",
+            "http://github.com/",
+            "git@github.com:",
+        ]
+        .iter()
+        .find_map(|prefix| source.strip_prefix(prefix));
+
+        if let Some(path) = github_path {
+            let (path, branch) = match path.split_once('#') {
+                Some((path, branch)) => (path, Some(branch)),
+                None => (path, None),
+            };
+            let (path, tree_branch) = match path.split_once("/tree/") {
+                Some((path, branch)) => (path, Some(branch)),
+                None => (path, None),
+            };
+
+            let repo = path.trim_end_matches('/').trim_end_matches(".git");
+
+            if repo.split('/').count() != 2 || repo.split('/').any(str::is_empty) {
+                return Err(t!("source.not_a_repo", source = source));
+            }
+
+            let branch = branch.or(tree_branch).unwrap_or(REPO_BRANCH);
+
+            return Ok(Source::GitHub {
+                repo: repo.to_owned(),
+                branch: branch.trim_end_matches('/').to_owned(),
+            });
+        }
+
+        let path = fs_util::expand_tilde(source);
+
+        if path.is_dir() {
+            Ok(Source::Local(path))
+        } else {
+            Err(t!("source.invalid", source = source))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn github(repo: &str, branch: &str) -> Source {
+        Source::GitHub {
+            repo: repo.to_owned(),
+            branch: branch.to_owned(),
+        }
+    }
+
+    #[test]
+    fn parses_github_urls() {
+        assert_eq!(
+            "https://github.com/owner/redwood".parse(),
+            Ok(github("owner/redwood", REPO_BRANCH))
+        );
+        assert_eq!(
+            "http://github.com/owner/redwood/".parse(),
+            Ok(github("owner/redwood", REPO_BRANCH))
+        );
+        assert_eq!(
+            "git@github.com:owner/redwood.git".parse(),
+            Ok(github("owner/redwood", REPO_BRANCH))
+        );
+    }
+
+    #[test]
+    fn parses_branches() {
+        assert_eq!(
+            "https://github.com/owner/redwood/tree/feat/rsc/".parse(),
+            Ok(github("owner/redwood", "feat/rsc"))
+        );
+        assert_eq!(
+            "https://github.com/owner/redwood.git#next".parse(),
+            Ok(github("owner/redwood", "next"))
+        );
+        assert_eq!(
+            "https://github.com/owner/redwood/tree/main#next".parse(),
+            Ok(github("owner/redwood", "next"))
+        );
+    }
+
+    #[test]
+    fn rejects_github_urls_that_arent_repos() {
+        assert!("https://github.com/owner".parse::<Source>().is_err());
+        assert!("https://github.com/owner/".parse::<Source>().is_err());
+        assert!("https://github.com//redwood".parse::<Source>().is_err());
+        assert!("https://github.com/owner/redwood/issues"
+            .parse::<Source>()
+            .is_err());
+    }
+
+    #[test]
+    fn parses_local_directories() {
+        let dir = std::env::temp_dir();
+
+        assert_eq!(
+            dir.to_string_lossy().parse(),
+            Ok(Source::Local(dir.clone()))
+        );
+        assert!(dir
+            .join("rw-quickstart-missing")
+            .to_string_lossy()
+            .parse::<Source>()
+            .is_err());
+    }
+}
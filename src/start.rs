@@ -0,0 +1,44 @@
+use std::path::Path;
+
+use crate::cleanup;
+use crate::events;
+use crate::exec::{display_command, exec_interactive_in};
+use crate::i18n::t;
+use crate::package_manager::PackageManager;
+
+/// How to run the app once it's been created
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartMode {
+    /// `rw dev`
+    Dev,
+    /// `rw build` followed by `rw serve`
+    Prod,
+}
+
+/// Runs the app in the foreground until it exits, and then exits with its
+/// exit code. The app's output goes straight to the terminal, and Ctrl-C is
+/// left for the app to handle
+pub fn run(mode: StartMode, package_manager: PackageManager, project_dir: &Path) -> ! {
+    let rw_commands: &[&[&str]] = match mode {
+        StartMode::Dev => &[&["rw", "dev"]],
+        StartMode::Prod => &[&["rw", "build"], &["rw", "serve"]],
+    };
+
+    cleanup::forward_interrupts();
+
+    for rw_command in rw_commands {
+        let mut command = package_manager.exec_prefix().to_vec();
+        command.extend_from_slice(rw_command);
+
+        events::status(t!("progress.start", command = display_command(&command)));
+
+        let status = exec_interactive_in(&command, project_dir);
+
+        if !status.success() {
+            // Killed by a signal (like Ctrl-C) if there's no exit code
+            std::process::exit(status.code().unwrap_or(130));
+        }
+    }
+
+    std::process::exit(0);
+}
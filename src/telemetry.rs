@@ -0,0 +1,121 @@
+use serde_json::{json, Value};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::i18n::t;
+use crate::{config_file, http, node, theme, Config};
+
+/// What the `telemetry` subcommand can do
+#[derive(clap::Subcommand, Debug)]
+pub enum Action {
+    /// Show whether usage stats are sent, and where
+    Status,
+    /// Stop sending usage stats, by turning telemetry off in the config file
+    Disable,
+}
+
+/// Where to send the stats. `None` unless the user has opted in
+static ENDPOINT: OnceLock<Option<String>> = OnceLock::new();
+/// How long the phases of the current run took, in milliseconds
+static PHASES: Mutex<Vec<(&'static str, u128)>> = Mutex::new(Vec::new());
+/// The phase that's running, which is the one that failed if we exit early
+static CURRENT_PHASE: Mutex<Option<&'static str>> = Mutex::new(None);
+
+/// Reporting is slower than it's worth to the user
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Enables reporting to `endpoint`, if `enabled`. Stats are only ever sent
+/// when the user has opted in, and never include paths, names or anything
+/// else about the project
+pub fn configure(enabled: bool, endpoint: Option<&str>) {
+    let endpoint = match (enabled, endpoint) {
+        (true, Some(endpoint)) => Some(endpoint.to_owned()),
+        (true, None) => {
+            tracing::warn!("{}", t!("telemetry.no_endpoint_warning"));
+            None
+        }
+        (false, _) => None,
+    };
+
+    ENDPOINT
+        .set(endpoint)
+        .expect("Telemetry can only be configured once");
+}
+
+pub fn phase_started(name: &'static str) {
+    *CURRENT_PHASE.lock().unwrap() = Some(name);
+}
+
+pub fn phase_finished(name: &'static str, duration_ms: u128) {
+    PHASES.lock().unwrap().push((name, duration_ms));
+    *CURRENT_PHASE.lock().unwrap() = None;
+}
+
+/// Sends how the run went, if the user has opted in. Failing to send isn't
+/// an error
+pub fn report(success: bool) {
+    let Some(Some(endpoint)) = ENDPOINT.get() else {
+        return;
+    };
+
+    let phases: serde_json::Map<String, Value> = PHASES
+        .lock()
+        .unwrap()
+        .drain(..)
+        .map(|(name, duration_ms)| (name.to_owned(), json!(duration_ms)))
+        .collect();
+
+    let event = json!({
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "node_version": node::version(),
+        "success": success,
+        "failed_phase": CURRENT_PHASE.lock().unwrap().take(),
+        "dry_run": Config::is_dry_run(),
+        "phases": phases,
+    });
+
+    tracing::debug!("Sending usage stats to {endpoint}: {event}");
+
+    let result = http::client()
+        .post(endpoint)
+        .timeout(TIMEOUT)
+        .json(&event)
+        .send();
+
+    if let Err(e) = result {
+        tracing::debug!("Could not send usage stats: {e}");
+    }
+}
+
+/// Runs the `telemetry` subcommand. `enabled` and `endpoint` are the
+/// effective settings, and `source` says where `enabled` came from
+pub fn run(action: &Action, enabled: bool, endpoint: Option<&str>, source: &str) {
+    match action {
+        Action::Status => {
+            if enabled {
+                println!("{}", t!("telemetry.enabled", source = source));
+                match endpoint {
+                    Some(endpoint) => {
+                        println!("{}", t!("telemetry.endpoint", endpoint = endpoint));
+                    }
+                    None => println!("{}", t!("telemetry.no_endpoint")),
+                }
+            } else {
+                println!("{}", t!("telemetry.disabled", source = source));
+            }
+        }
+        Action::Disable => {
+            let path = config_file::set("telemetry", toml::Value::Boolean(false));
+            println!(
+                "{}",
+                t!("telemetry.disabled_in", file = path.to_string_lossy())
+            );
+
+            if source.contains("RW_QUICKSTART_TELEMETRY") {
+                eprintln!("{}", theme::warning(t!("telemetry.env_takes_precedence")));
+            }
+        }
+    }
+}
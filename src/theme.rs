@@ -0,0 +1,62 @@
+use console::{style, StyledObject};
+use std::fmt::Display;
+
+/// Turns colors off with `--no-color` or a non-empty `NO_COLOR`. Otherwise
+/// they're used if the output is a terminal, which `console` detects for
+/// stdout and stderr separately
+pub fn configure(no_color: bool) {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+
+    if no_color || no_color_env {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+}
+
+/// Stops colors from ending up in output meant for other programs, like the
+/// JSON events
+pub fn disable_stdout_colors() {
+    console::set_colors_enabled(false);
+}
+
+/// A passed check, like `✓ Node v20.11.0`
+pub fn success<D: Display>(message: D) -> String {
+    format!("{} {message}", style("✓").green())
+}
+
+/// A failed check, like `✗ Could not find git`
+pub fn failure<D: Display>(message: D) -> String {
+    format!("{} {message}", style("✗").red())
+}
+
+/// Highlights the start of a happy message, like "Done!"
+pub fn highlight<D>(message: D) -> StyledObject<D> {
+    style(message).green().bold()
+}
+
+/// The headline of an error printed to stderr. Any explanation or guidance
+/// that follows is printed without styling
+pub fn error<D>(message: D) -> StyledObject<D> {
+    style(message).red().bold().for_stderr()
+}
+
+/// Something that went wrong, but that we can recover from, printed to
+/// stderr
+pub fn warning<D>(message: D) -> StyledObject<D> {
+    style(message).yellow().for_stderr()
+}
+
+/// A line that was removed, in a diff
+pub fn removed<D>(message: D) -> StyledObject<D> {
+    style(message).red()
+}
+
+/// A line that was added, in a diff
+pub fn added<D>(message: D) -> StyledObject<D> {
+    style(message).green()
+}
+
+/// Output from commands we run, which is less important than our own
+pub fn dim<D>(message: D) -> StyledObject<D> {
+    style(message).dim()
+}
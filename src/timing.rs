@@ -0,0 +1,178 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::events::{self, OutputFormat};
+use crate::i18n::t;
+use crate::{theme, Config};
+
+static PROFILE: OnceLock<Option<PathBuf>> = OnceLock::new();
+/// Every phase that's finished, in the order they finished
+static PHASES: Mutex<Vec<Timing>> = Mutex::new(Vec::new());
+/// The phase that's running, and when it started
+static CURRENT_PHASE: Mutex<Option<(&'static str, Instant)>> = Mutex::new(None);
+/// How many of `PHASES` have been in a summary already. Batch runs print one
+/// per project
+static SUMMARIZED: Mutex<usize> = Mutex::new(0);
+
+struct Timing {
+    name: &'static str,
+    duration: Duration,
+    /// What the phase reported in its `phase_end` event, like how many bytes
+    /// were downloaded
+    data: Value,
+}
+
+/// Writes a JSON timing report to `profile` at the end of the run, and when
+/// it fails
+pub fn configure(profile: Option<&Path>) {
+    PROFILE
+        .set(profile.map(Path::to_path_buf))
+        .expect("Timing can only be configured once");
+}
+
+pub fn phase_started(name: &'static str) {
+    *CURRENT_PHASE.lock().unwrap() = Some((name, Instant::now()));
+}
+
+pub fn phase_finished(name: &'static str, duration: Duration, data: &Value) {
+    PHASES.lock().unwrap().push(Timing {
+        name,
+        duration,
+        data: data.clone(),
+    });
+    *CURRENT_PHASE.lock().unwrap() = None;
+}
+
+/// Prints how long each phase of the project that was just created took,
+/// and writes the report. The summary is left out of dry runs, which don't
+/// do the slow parts, and out of `--quiet` and JSON output, where the
+/// `phase_end` events have the same numbers
+pub fn finish_project() {
+    write_profile(false);
+
+    let phases = PHASES.lock().unwrap();
+    let mut summarized = SUMMARIZED.lock().unwrap();
+    let new_phases = &phases[*summarized..];
+    *summarized = phases.len();
+
+    if Config::is_dry_run()
+        || Config::is_quiet()
+        || Config::output_format() == OutputFormat::Json
+        || new_phases.is_empty()
+    {
+        return;
+    }
+
+    let width = new_phases
+        .iter()
+        .map(|phase| phase.name.len())
+        .max()
+        .unwrap_or(0);
+    let total: Duration = new_phases.iter().map(|phase| phase.duration).sum();
+
+    events::status(theme::dim(t!("timing.time_spent")));
+
+    for phase in new_phases {
+        let mut line = format!(
+            "  {:width$}  {:>7}",
+            phase.name,
+            format_duration(phase.duration)
+        );
+
+        if let Some(rate) = download_rate(phase) {
+            line.push_str(&format!("  ({rate})"));
+        }
+
+        events::status(theme::dim(line));
+    }
+
+    events::status(theme::dim(format!(
+        "  {:width$}  {:>7}",
+        "total",
+        format_duration(total)
+    )));
+}
+
+/// Writes the timing report, if `--profile` was given. A run that `failed`
+/// includes the phase it failed in, up to the failure
+pub fn write_profile(failed: bool) {
+    let Some(Some(path)) = PROFILE.get() else {
+        return;
+    };
+
+    let mut phases: Vec<Value> = PHASES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|phase| {
+            let mut entry = json!({
+                "phase": phase.name,
+                "duration_ms": phase.duration.as_millis(),
+            });
+
+            if let (Some(entry), Value::Object(data)) = (entry.as_object_mut(), &phase.data) {
+                entry.extend(data.clone());
+            }
+
+            entry
+        })
+        .collect();
+
+    if let (true, Some((name, start))) = (failed, *CURRENT_PHASE.lock().unwrap()) {
+        phases.push(json!({
+            "phase": name,
+            "duration_ms": start.elapsed().as_millis(),
+            "failed": true,
+        }));
+    }
+
+    let total_ms: u128 = phases
+        .iter()
+        .filter_map(|phase| phase.get("duration_ms").and_then(Value::as_u64))
+        .map(u128::from)
+        .sum();
+
+    let report = json!({
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "success": !failed,
+        "total_ms": total_ms,
+        "phases": phases,
+    });
+
+    let contents = serde_json::to_string_pretty(&report).expect("Failed to serialize the profile");
+
+    if let Err(e) = fs::write(path, contents + "\n") {
+        eprintln!(
+            "{}",
+            theme::warning(t!(
+                "timing.cant_write_profile",
+                file = path.to_string_lossy(),
+                error = e
+            ))
+        );
+    }
+}
+
+/// Like `1.2s` or `2m 5s`
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+
+    if seconds < 60.0 {
+        format!("{seconds:.1}s")
+    } else {
+        format!("{}m {}s", duration.as_secs() / 60, duration.as_secs() % 60)
+    }
+}
+
+/// How fast a download was, which tells a slow network apart from a slow
+/// disk. Only buffered downloads know their size
+fn download_rate(phase: &Timing) -> Option<String> {
+    let bytes = phase.data.get("bytes").and_then(Value::as_u64)?;
+    let seconds = phase.duration.as_secs_f64();
+
+    (phase.name == "download" && seconds > 0.0)
+        .then(|| format!("{:.1} MB/s", bytes as f64 / 1_000_000.0 / seconds))
+}
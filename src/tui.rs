@@ -0,0 +1,526 @@
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::io::{self, IsTerminal, Stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::i18n::t;
+use crate::{cleanup, theme};
+
+/// How often the screen is redrawn, and keys are checked for
+const TICK: Duration = Duration::from_millis(100);
+/// How much of a step's log is kept. `yarn install` can print a lot
+const MAX_LOG_LINES: usize = 5000;
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static STATE: Mutex<State> = Mutex::new(State::new());
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// What's on the screen
+struct State {
+    title: String,
+    steps: Vec<Step>,
+    /// The step whose log is shown. `None` follows the running step
+    selected: Option<usize>,
+    /// Whether the log takes up the whole screen
+    expanded: bool,
+    /// How many lines the expanded log is scrolled up from the bottom
+    scroll: usize,
+    /// Everything printed to stderr while the TUI was up, to be printed
+    /// again once it's closed
+    stderr: Vec<String>,
+}
+
+struct Step {
+    name: &'static str,
+    start: Instant,
+    result: StepResult,
+    /// The latest status message, like which plugin is running
+    detail: Option<String>,
+    log: Vec<String>,
+    /// Bytes done, out of how many if that's known
+    progress: Option<(u64, Option<u64>)>,
+}
+
+enum StepResult {
+    Running,
+    Done(Duration),
+    Failed,
+}
+
+/// The render thread, and where stderr went before it was captured
+struct Session {
+    renderer: JoinHandle<()>,
+    #[cfg(unix)]
+    stderr: Option<stderr::Capture>,
+}
+
+impl State {
+    const fn new() -> State {
+        State {
+            title: String::new(),
+            steps: Vec::new(),
+            selected: None,
+            expanded: false,
+            scroll: 0,
+            stderr: Vec::new(),
+        }
+    }
+
+    /// Adds a line to the log of the running step, or of the one that ran
+    /// last if it's in between steps
+    fn log(&mut self, line: &str) {
+        let Some(step) = self.steps.last_mut() else {
+            return;
+        };
+
+        step.log.push(console::strip_ansi_codes(line).into_owned());
+
+        if step.log.len() > MAX_LOG_LINES {
+            step.log.drain(..step.log.len() - MAX_LOG_LINES);
+        }
+    }
+
+    fn running_step(&mut self) -> Option<&mut Step> {
+        self.steps
+            .last_mut()
+            .filter(|step| matches!(step.result, StepResult::Running))
+    }
+
+    fn shown_step(&self) -> Option<usize> {
+        self.selected
+            .or_else(|| self.steps.len().checked_sub(1))
+            .filter(|i| *i < self.steps.len())
+    }
+}
+
+/// Takes over the terminal, showing the pipeline as a checklist of its
+/// steps, with the log of the running one below it. The steps are the
+/// phases of the `phase_start` and `phase_end` events.
+///
+/// Everything that would be printed while it's up is shown in it instead.
+/// That includes stderr on Unix, which is printed again once it's closed,
+/// so warnings and errors aren't lost. Does nothing if stdout isn't a
+/// terminal
+pub fn start(project: &str) {
+    if !io::stdout().is_terminal() {
+        eprintln!("{}", theme::warning(t!("tui.not_a_terminal")));
+        return;
+    }
+
+    let terminal = match open_terminal() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            restore_terminal();
+            eprintln!("{}", theme::warning(t!("tui.cant_start", error = e)));
+            return;
+        }
+    };
+
+    *STATE.lock().unwrap() = State {
+        title: t!("tui.title", project = project),
+        ..State::new()
+    };
+    ACTIVE.store(true, Ordering::SeqCst);
+
+    *SESSION.lock().unwrap() = Some(Session {
+        renderer: thread::spawn(move || render(terminal)),
+        #[cfg(unix)]
+        stderr: stderr::capture(),
+    });
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/// Closes the TUI and gives the terminal back. What it showed is printed
+/// as a plain checklist, followed by anything that was printed to stderr.
+/// With `failed`, the running step is marked as the one that failed
+pub fn stop(failed: bool) {
+    let Some(session) = SESSION.lock().unwrap().take() else {
+        return;
+    };
+
+    ACTIVE.store(false, Ordering::SeqCst);
+    session.renderer.join().ok();
+
+    #[cfg(unix)]
+    if let Some(capture) = session.stderr {
+        capture.restore();
+    }
+
+    // This also runs when something panics, maybe while holding the lock
+    let mut state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if let Some(step) = state.running_step() {
+        step.result = if failed {
+            StepResult::Failed
+        } else {
+            StepResult::Done(step.start.elapsed())
+        };
+    }
+
+    for step in &state.steps {
+        match step.result {
+            StepResult::Failed => println!("{}", theme::failure(label(step.name))),
+            _ => println!("{}", theme::success(label(step.name))),
+        }
+    }
+
+    for line in state.stderr.drain(..) {
+        eprintln!("{line}");
+    }
+}
+
+pub fn phase_started(name: &'static str) {
+    STATE.lock().unwrap().steps.push(Step {
+        name,
+        start: Instant::now(),
+        result: StepResult::Running,
+        detail: None,
+        log: Vec::new(),
+        progress: None,
+    });
+}
+
+pub fn phase_finished(duration: Duration) {
+    if let Some(step) = STATE.lock().unwrap().running_step() {
+        step.result = StepResult::Done(duration);
+    }
+}
+
+/// Shows `message` next to the running step, and in its log
+pub fn status(message: &str) {
+    let mut state = STATE.lock().unwrap();
+
+    if let Some(step) = state.running_step() {
+        step.detail = Some(console::strip_ansi_codes(message).into_owned());
+    }
+
+    state.log(message);
+}
+
+/// How much of a download is done
+pub fn progress(done: u64, total: Option<u64>) {
+    if let Some(step) = STATE.lock().unwrap().running_step() {
+        step.progress = Some((done, total));
+    }
+}
+
+/// Adds the lines a command prints to the running step's log, as they come
+/// in. Returns all of them once the command is done
+pub fn show_lines(lines: mpsc::Receiver<String>) -> String {
+    let mut output = String::new();
+
+    for line in lines {
+        STATE.lock().unwrap().log(&line);
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    output
+}
+
+fn open_terminal() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    terminal.hide_cursor()?;
+    terminal.clear()?;
+
+    Ok(terminal)
+}
+
+fn restore_terminal() {
+    disable_raw_mode().ok();
+    execute!(io::stdout(), LeaveAlternateScreen).ok();
+}
+
+/// Redraws the screen and handles keys until the TUI is stopped
+fn render(mut terminal: Terminal<CrosstermBackend<Stdout>>) {
+    let mut spinner = SPINNER_FRAMES.iter().cycle();
+
+    while is_active() {
+        let frame = *spinner.next().unwrap();
+        terminal
+            .draw(|f| draw(f, &STATE.lock().unwrap(), frame))
+            .ok();
+
+        if event::poll(TICK).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                if key.kind == KeyEventKind::Press {
+                    handle_key(key.code, key.modifiers);
+                }
+            }
+        }
+    }
+
+    terminal.show_cursor().ok();
+    restore_terminal();
+}
+
+fn handle_key(code: KeyCode, modifiers: KeyModifiers) {
+    // The terminal is in raw mode, so Ctrl-C is a key rather than a signal.
+    // Cleaning up closes the TUI, which waits for this thread
+    if code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL) {
+        thread::spawn(cleanup::interrupt);
+        return;
+    }
+
+    let mut state = STATE.lock().unwrap();
+    let last = state.steps.len().saturating_sub(1);
+    let shown = state.shown_step().unwrap_or(0);
+
+    match code {
+        KeyCode::Up | KeyCode::Char('k') => {
+            state.selected = Some(shown.saturating_sub(1));
+            state.scroll = 0;
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            state.selected = Some((shown + 1).min(last));
+            state.scroll = 0;
+        }
+        KeyCode::Enter | KeyCode::Char(' ') => {
+            state.expanded = !state.expanded;
+            state.scroll = 0;
+        }
+        KeyCode::PageUp => state.scroll += 10,
+        KeyCode::PageDown => state.scroll = state.scroll.saturating_sub(10),
+        KeyCode::Esc => {
+            state.selected = None;
+            state.expanded = false;
+            state.scroll = 0;
+        }
+        _ => {}
+    }
+}
+
+fn draw(frame: &mut Frame, state: &State, spinner: char) {
+    let dim = Style::default().add_modifier(Modifier::DIM);
+    let steps_height = if state.expanded {
+        0
+    } else {
+        state.steps.len() as u16
+    };
+
+    let [title_area, steps_area, log_area, help_area] = Layout::vertical([
+        Constraint::Length(2),
+        Constraint::Length(steps_height),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(
+        Paragraph::new(
+            Line::from(state.title.as_str()).style(Style::default().add_modifier(Modifier::BOLD)),
+        ),
+        title_area,
+    );
+
+    let shown = state.shown_step();
+    let steps: Vec<Line> = state
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| step_line(step, spinner, state.selected == Some(i)))
+        .collect();
+    frame.render_widget(Paragraph::new(steps), steps_area);
+
+    if let Some(step) = shown.map(|i| &state.steps[i]) {
+        draw_log(frame, step, state.scroll, log_area);
+    }
+
+    let help = if state.expanded {
+        t!("tui.help_expanded")
+    } else {
+        t!("tui.help")
+    };
+    frame.render_widget(Paragraph::new(Span::styled(help, dim)), help_area);
+}
+
+/// Like `✓ Installing the dependencies  12.3s`
+fn step_line(step: &Step, spinner: char, selected: bool) -> Line<'static> {
+    let dim = Style::default().add_modifier(Modifier::DIM);
+
+    let (icon, icon_style) = match step.result {
+        StepResult::Running => (spinner, Style::default().fg(Color::Yellow)),
+        StepResult::Done(_) => ('✓', Style::default().fg(Color::Green)),
+        StepResult::Failed => ('✗', Style::default().fg(Color::Red)),
+    };
+    let duration = match step.result {
+        StepResult::Done(duration) => duration,
+        _ => step.start.elapsed(),
+    };
+
+    let mut label_style = Style::default();
+    if selected {
+        label_style = label_style.add_modifier(Modifier::REVERSED);
+    }
+
+    let mut spans = vec![
+        Span::styled(format!(" {icon} "), icon_style),
+        Span::styled(label(step.name), label_style),
+        Span::styled(format!("  {:.1}s", duration.as_secs_f64()), dim),
+    ];
+
+    if let StepResult::Running = step.result {
+        if let Some(progress) = step.progress {
+            spans.push(Span::raw(format!("  {}", format_progress(progress))));
+        }
+
+        if let Some(detail) = &step.detail {
+            spans.push(Span::styled(format!("  {detail}"), dim));
+        }
+    }
+
+    Line::from(spans)
+}
+
+/// The end of the step's log, `scroll` lines up from the bottom
+fn draw_log(frame: &mut Frame, step: &Step, scroll: usize, area: Rect) {
+    let height = area.height.saturating_sub(2) as usize;
+    let end = step.log.len().saturating_sub(scroll);
+    let start = end.saturating_sub(height);
+
+    let lines: Vec<Line> = step.log[start..end]
+        .iter()
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+
+    let block = Block::bordered()
+        .title(format!(" {} ", label(step.name)))
+        .border_style(Style::default().add_modifier(Modifier::DIM));
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Like `43% of 28.6 MB`
+fn format_progress((done, total): (u64, Option<u64>)) -> String {
+    let mb = |bytes: u64| bytes as f64 / 1_000_000.0;
+
+    match total {
+        Some(total) if total > 0 => t!(
+            "tui.progress",
+            percent = done * 100 / total,
+            megabytes = format!("{:.1}", mb(total))
+        ),
+        _ => t!("tui.megabytes", megabytes = format!("{:.1}", mb(done))),
+    }
+}
+
+/// What a phase does, for people
+fn label(phase: &str) -> String {
+    match phase {
+        "check" => t!("phase.check"),
+        "download" => t!("phase.download"),
+        "extract" => t!("phase.extract"),
+        "patch" => t!("phase.patch"),
+        "lockfile" => t!("phase.lockfile"),
+        "install" => t!("phase.install"),
+        "convert" => t!("phase.convert"),
+        "database" => t!("phase.database"),
+        "plugin" => t!("phase.plugin"),
+        "report" => t!("phase.report"),
+        "hooks" => t!("phase.hooks"),
+        "git" => t!("phase.git"),
+        phase => phase.to_owned(),
+    }
+}
+
+/// Sends stderr to the TUI instead of the terminal, where it would end up
+/// in the middle of the screen
+#[cfg(unix)]
+mod stderr {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::os::fd::{FromRawFd, RawFd};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::STATE;
+
+    /// How long to wait for the last of what was captured
+    const DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+
+    pub struct Capture {
+        /// The real stderr
+        saved: RawFd,
+        /// Closed once everything written to the pipe has been read
+        done: mpsc::Receiver<()>,
+    }
+
+    /// Points stderr at a pipe that's read into the TUI. `None` if that
+    /// fails, in which case stderr is left alone
+    pub fn capture() -> Option<Capture> {
+        let mut fds: [RawFd; 2] = [0; 2];
+
+        // SAFETY: `pipe` writes two fds to the array it's given, which is
+        // long enough. `dup` and `dup2` only take fds
+        let saved = unsafe {
+            if libc::pipe(fds.as_mut_ptr()) != 0 {
+                return None;
+            }
+
+            let saved = libc::dup(libc::STDERR_FILENO);
+            if saved < 0 || libc::dup2(fds[1], libc::STDERR_FILENO) < 0 {
+                libc::close(fds[0]);
+                libc::close(fds[1]);
+                return None;
+            }
+            libc::close(fds[1]);
+
+            saved
+        };
+
+        // SAFETY: The read end of the pipe is ours alone
+        let reader = unsafe { File::from_raw_fd(fds[0]) };
+        let (tx, done) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                let mut state = STATE.lock().unwrap();
+                state.log(&line);
+                state.stderr.push(line);
+            }
+
+            drop(tx);
+        });
+
+        Some(Capture { saved, done })
+    }
+
+    impl Capture {
+        /// Points stderr back at the terminal, after reading what's left in
+        /// the pipe. Children that are still running could keep it open, so
+        /// that's only waited for for a little while
+        pub fn restore(self) {
+            // SAFETY: `saved` is the fd `capture` duplicated stderr to
+            unsafe {
+                libc::dup2(self.saved, libc::STDERR_FILENO);
+                libc::close(self.saved);
+            }
+
+            self.done.recv_timeout(DRAIN_TIMEOUT).ok();
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use crate::diagnostics;
+use crate::exec::{display_command, exec_in, exec_streaming_in};
+use crate::i18n::t;
+use crate::package_json::{find_package_jsons, update_package_jsons};
+use crate::package_manager::PackageManager;
+use crate::provenance;
+use crate::registry::get_tagged_version;
+use crate::theme;
+
+/// Bumps all @redwoodjs/* dependencies in an existing project to the latest
+/// canary, reinstalls, and commits the result.
+///
+/// Projects we created are upgraded along the dist-tag they were created
+/// with, as recorded in their provenance file
+pub fn run(project_dir: &str, skip_install: bool, skip_git: bool) {
+    if !Path::new(project_dir).join("package.json").exists() {
+        eprintln!(
+            "{}",
+            theme::error(t!("upgrade.no_package_json", dir = project_dir))
+        );
+        diagnostics::fail();
+    }
+
+    let use_git = !skip_git && Path::new(project_dir).join(".git").exists();
+
+    // Refuse to mix the version bump with the user's own changes in the same
+    // commit
+    if use_git
+        && !exec_in(&["git", "status", "--porcelain"], project_dir)
+            .trim()
+            .is_empty()
+    {
+        eprintln!(
+            "{}",
+            theme::error(t!("upgrade.uncommitted_changes", dir = project_dir))
+        );
+        eprintln!("{}", t!("upgrade.uncommitted_changes_hint"));
+        diagnostics::fail();
+    }
+
+    let provenance = provenance::read(Path::new(project_dir));
+    let redwood_tag = provenance
+        .as_ref()
+        .and_then(|json| json.get("redwood_tag"))
+        .and_then(|tag| tag.as_str())
+        .unwrap_or("canary")
+        .to_owned();
+
+    if let Some(json) = &provenance {
+        println!(
+            "{}",
+            t!("upgrade.created_from", source = provenance::describe(json))
+        );
+    }
+
+    let latest_rw_canary = get_tagged_version("@redwoodjs/core", &redwood_tag);
+    println!("{}", t!("upgrade.upgrading", version = latest_rw_canary));
+
+    update_package_jsons(
+        find_package_jsons(project_dir),
+        latest_rw_canary.clone(),
+        true,
+    );
+    provenance::record_upgrade(Path::new(project_dir), &latest_rw_canary);
+
+    if !skip_install {
+        let package_manager = PackageManager::from_project(project_dir);
+        let install_command = package_manager.install_command();
+
+        println!(
+            "{}",
+            t!(
+                "progress.install",
+                command = display_command(&install_command)
+            )
+        );
+        exec_streaming_in(&install_command, project_dir);
+    }
+
+    if use_git {
+        println!("{}", t!("upgrade.committing"));
+        let message = format!("Upgrade Redwood to {latest_rw_canary}");
+        exec_in(&["git", "add", "."], project_dir);
+        exec_in(&["git", "commit", "-m", &message], project_dir);
+    } else {
+        tracing::debug!("Not committing the upgrade");
+    }
+
+    println!(
+        "{} {}",
+        theme::highlight(t!("done.done")),
+        t!(
+            "upgrade.done",
+            dir = project_dir,
+            version = latest_rw_canary
+        )
+    );
+}
@@ -0,0 +1,78 @@
+use dialoguer::{Confirm, Input};
+use std::path::Path;
+
+use crate::exec::display_command;
+use crate::i18n::t;
+use crate::language::Language;
+use crate::Args;
+
+/// Asks for everything that's needed to create a project, using the values
+/// from the command line (or config file) as defaults
+pub fn run(args: &mut Args) {
+    println!("{}", t!("wizard.welcome"));
+    println!();
+
+    let installation_dir: String = Input::new()
+        .with_prompt(t!("wizard.installation_dir"))
+        .default("redwood-rsc-app".to_owned())
+        .validate_with(|dir: &String| {
+            if dir.trim().is_empty() {
+                Err(t!("wizard.enter_a_directory"))
+            } else if Path::new(dir.trim()).is_file() {
+                Err(t!("wizard.is_a_file"))
+            } else {
+                Ok(())
+            }
+        })
+        .interact_text()
+        .unwrap_or_else(|e| exit_wizard(e));
+
+    args.template = Input::new()
+        .with_prompt(t!("wizard.template"))
+        .default(args.template.clone())
+        .interact_text()
+        .unwrap_or_else(|e| exit_wizard(e));
+
+    args.redwood_tag = Input::new()
+        .with_prompt(t!("wizard.redwood_tag"))
+        .default(args.redwood_tag.clone())
+        .interact_text()
+        .unwrap_or_else(|e| exit_wizard(e));
+
+    args.skip_install = !Confirm::new()
+        .with_prompt(t!(
+            "wizard.install",
+            command = display_command(&args.package_manager.install_command())
+        ))
+        .default(!args.skip_install)
+        .interact()
+        .unwrap_or_else(|e| exit_wizard(e));
+
+    // The conversion to JavaScript needs the dependencies
+    if !args.skip_install {
+        let use_js = Confirm::new()
+            .with_prompt(t!("wizard.javascript"))
+            .default(args.language == Language::Js)
+            .interact()
+            .unwrap_or_else(|e| exit_wizard(e));
+
+        args.language = if use_js { Language::Js } else { Language::Ts };
+    } else {
+        args.language = Language::Ts;
+    }
+
+    args.skip_git = !Confirm::new()
+        .with_prompt(t!("wizard.git"))
+        .default(!args.skip_git)
+        .interact()
+        .unwrap_or_else(|e| exit_wizard(e));
+
+    args.installation_dir = Some(installation_dir.trim().to_owned());
+
+    println!();
+}
+
+fn exit_wizard(e: dialoguer::Error) -> ! {
+    eprintln!("{}", t!("wizard.read_failed", error = e));
+    std::process::exit(1);
+}
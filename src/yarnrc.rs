@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::Path;
+
+/// How yarn installs the project's dependencies
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeLinker {
+    /// Plug'n'Play, without a node_modules directory
+    Pnp,
+    /// A regular node_modules directory. Works with the most tools
+    NodeModules,
+    /// node_modules with symlinks into a global store, like pnpm
+    Pnpm,
+}
+
+impl NodeLinker {
+    fn value(&self) -> &'static str {
+        match self {
+            NodeLinker::Pnp => "pnp",
+            NodeLinker::NodeModules => "node-modules",
+            NodeLinker::Pnpm => "pnpm",
+        }
+    }
+}
+
+/// Settings to write to the project's `.yarnrc.yml`
+#[derive(Debug, Default)]
+pub struct Options {
+    pub node_linker: Option<NodeLinker>,
+    pub global_cache: Option<bool>,
+}
+
+impl Options {
+    /// The settings as `.yarnrc.yml` keys and values
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let mut settings = Vec::new();
+
+        if let Some(node_linker) = self.node_linker {
+            settings.push(("nodeLinker", node_linker.value().to_owned()));
+        }
+
+        if let Some(global_cache) = self.global_cache {
+            settings.push(("enableGlobalCache", global_cache.to_string()));
+        }
+
+        settings
+    }
+}
+
+/// Sets the options in `.yarnrc.yml`, creating it if needed. Top level keys
+/// that are already there are updated in place, so the rest of the file,
+/// including comments, is left as it is
+pub fn configure(project_dir: &Path, options: &Options) {
+    let settings = options.settings();
+
+    if settings.is_empty() {
+        return;
+    }
+
+    let path = project_dir.join(".yarnrc.yml");
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+    let line_ending = if contents.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let mut lines: Vec<String> = contents.lines().map(str::to_owned).collect();
+
+    for (key, value) in settings {
+        tracing::debug!("Setting {key} to {value} in {}", path.to_string_lossy());
+
+        let line = format!("{key}: {value}");
+        let existing = lines
+            .iter_mut()
+            .find(|line| line.split_once(':').is_some_and(|(k, _)| k == key));
+
+        match existing {
+            Some(existing) => *existing = line,
+            None => lines.push(line),
+        }
+    }
+
+    let mut contents = lines.join(line_ending);
+    contents.push_str(line_ending);
+
+    fs::write(&path, contents).expect("Failed to write .yarnrc.yml");
+}
@@ -0,0 +1,296 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::confirm;
+use crate::exec::{self, display_command, exec};
+use crate::i18n::t;
+
+/// How much of a binary is read to tell whether it's a script, and what it
+/// runs. Shims are a few lines long
+const SCRIPT_PREFIX_SIZE: u64 = 4096;
+
+/// How a yarn binary was installed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    Corepack,
+    Homebrew,
+    /// `npm install --global yarn`, into this prefix
+    NpmGlobal(PathBuf),
+    Volta,
+    Unknown,
+}
+
+/// A yarn binary in PATH
+#[derive(Debug)]
+pub struct Yarn {
+    /// Where it is in PATH
+    pub path: PathBuf,
+    pub origin: Origin,
+}
+
+impl Origin {
+    /// Tells the origin from what the binary is, rather than from what its
+    /// path looks like. Symlinks are followed, asdf's shims are asked what
+    /// they run, and corepack's are recognized by leading into corepack's
+    /// own `dist` dir, or by running its code.
+    ///
+    /// Volta's shims all point at the same `volta-shim` binary, so they're
+    /// recognized by that, or by being in Volta's home
+    fn detect(path: &Path, canonical: &Path, corepack_dist: Option<&Path>) -> Origin {
+        if is_volta_shim(path, canonical) {
+            return Origin::Volta;
+        }
+
+        let script = read_script(canonical).unwrap_or_default();
+
+        if script.contains("asdf exec") {
+            let resolved = asdf_which_yarn()
+                .and_then(|resolved| Some((fs::canonicalize(&resolved).ok()?, resolved)))
+                .filter(|(resolved, _)| resolved != canonical);
+
+            return match resolved {
+                Some((canonical, path)) => Origin::detect(&path, &canonical, corepack_dist),
+                None => Origin::Unknown,
+            };
+        }
+
+        let is_corepack = corepack_dist.is_some_and(|dist| canonical.starts_with(dist))
+            || is_corepack_script(&script);
+
+        if is_corepack {
+            Origin::Corepack
+        } else if is_homebrew(canonical) {
+            Origin::Homebrew
+        } else if let Some(prefix) = npm_prefix(canonical) {
+            Origin::NpmGlobal(prefix)
+        } else {
+            Origin::Unknown
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Origin::Corepack => "corepack".to_owned(),
+            Origin::Homebrew => "Homebrew".to_owned(),
+            Origin::NpmGlobal(_) => t!("yarn.origin.npm_global"),
+            Origin::Volta => "Volta".to_owned(),
+            Origin::Unknown => t!("yarn.origin.unknown"),
+        }
+    }
+
+    /// How to get this yarn out of the way of corepack's. `None` for
+    /// corepack's own
+    pub fn guidance(&self) -> Option<String> {
+        let guidance = match self {
+            Origin::Corepack => return None,
+            Origin::Volta => t!("yarn.guidance.volta"),
+            Origin::Unknown => t!("yarn.guidance.unknown"),
+            Origin::Homebrew | Origin::NpmGlobal(_) => {
+                let command = self.uninstall_command()?;
+                t!(
+                    "yarn.guidance.remove",
+                    command =
+                        display_command(&command.iter().map(String::as_str).collect::<Vec<_>>())
+                )
+            }
+        };
+
+        Some(guidance)
+    }
+
+    /// The command that removes this yarn. `None` for corepack's, which is
+    /// the one to keep, and for yarns we don't know how to remove
+    pub fn uninstall_command(&self) -> Option<Vec<String>> {
+        let command: Vec<String> = match self {
+            Origin::Homebrew => ["brew", "uninstall", "yarn"].map(str::to_owned).to_vec(),
+            Origin::NpmGlobal(prefix) => vec![
+                "npm".to_owned(),
+                "uninstall".to_owned(),
+                "--global".to_owned(),
+                "--prefix".to_owned(),
+                prefix.to_string_lossy().into_owned(),
+                "yarn".to_owned(),
+            ],
+            Origin::Volta => ["volta", "uninstall", "yarn"].map(str::to_owned).to_vec(),
+            Origin::Corepack | Origin::Unknown => return None,
+        };
+
+        Some(command)
+    }
+
+    /// Whether the uninstall only removes the yarn package, and can be run
+    /// for the user. Homebrew and Volta have other things depending on
+    /// them, so those are left to the user
+    fn is_safe_to_uninstall(&self) -> bool {
+        matches!(self, Origin::NpmGlobal(_))
+    }
+}
+
+/// Every yarn in PATH, in PATH order. Binaries that are in PATH more than
+/// once are only included the first time
+pub fn find_all() -> Vec<Yarn> {
+    let mut seen = BTreeSet::new();
+    let corepack_dist = corepack_dist_dir();
+    tracing::debug!("corepack's dist dir: {corepack_dist:?}");
+
+    which::which_all("yarn")
+        .into_iter()
+        .flatten()
+        .filter_map(|path| {
+            let canonical = fs::canonicalize(&path).ok()?;
+
+            seen.insert(canonical.clone()).then(|| Yarn {
+                origin: Origin::detect(&path, &canonical, corepack_dist.as_deref()),
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Lists every yarn in PATH, with how to remove the ones that aren't from
+/// corepack
+pub fn describe_all() -> String {
+    let mut lines = vec![t!("yarn.all_in_path")];
+
+    for yarn in find_all() {
+        lines.push(format!(
+            "  {} ({})",
+            yarn.path.to_string_lossy(),
+            yarn.origin.name()
+        ));
+
+        if let Some(guidance) = yarn.origin.guidance() {
+            lines.push(format!("    {guidance}"));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Offers to remove the yarns that aren't from corepack and are safe to
+/// remove for the user. Returns whether any were removed
+pub fn remove_extra_yarns() -> bool {
+    let mut removed = false;
+
+    for yarn in find_all() {
+        let Some(command) = yarn.origin.uninstall_command() else {
+            continue;
+        };
+
+        if !yarn.origin.is_safe_to_uninstall() {
+            continue;
+        }
+
+        let command: Vec<&str> = command.iter().map(String::as_str).collect();
+        let question = t!(
+            "yarn.remove_question",
+            path = yarn.path.to_string_lossy(),
+            command = display_command(&command)
+        );
+
+        if confirm(&question) {
+            exec(&command);
+            removed = true;
+        }
+    }
+
+    removed
+}
+
+/// Where corepack keeps the scripts its shims run, like
+/// `/usr/local/lib/node_modules/corepack/dist`. Found by following the
+/// `corepack` in PATH. On Windows that's a `.cmd` script next to
+/// `node.exe`, with corepack in the `node_modules` dir next to it
+fn corepack_dist_dir() -> Option<PathBuf> {
+    let corepack = fs::canonicalize(which::which("corepack").ok()?).ok()?;
+    let dir = corepack.parent()?;
+
+    [
+        dir.to_path_buf(),
+        dir.join("node_modules").join("corepack").join("dist"),
+    ]
+    .into_iter()
+    .find(|dist| dist.join("corepack.js").is_file())
+}
+
+/// The start of `path`, if it's a script rather than a binary
+fn read_script(path: &Path) -> Option<String> {
+    let mut start = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(SCRIPT_PREFIX_SIZE)
+        .read_to_end(&mut start)
+        .ok()?;
+
+    (!start.contains(&0)).then(|| String::from_utf8_lossy(&start).replace('\\', "/"))
+}
+
+/// corepack's own `yarn.js`, wherever it's linked from, and the `.cmd` and
+/// `.ps1` shims that call it on Windows
+fn is_corepack_script(script: &str) -> bool {
+    script.contains("corepack.cjs") || script.contains("corepack/dist/")
+}
+
+fn is_volta_shim(path: &Path, canonical: &Path) -> bool {
+    let is_shim_binary = canonical
+        .file_stem()
+        .is_some_and(|stem| stem.eq_ignore_ascii_case("volta-shim"));
+    let volta_home = std::env::var_os("VOLTA_HOME").map(PathBuf::from);
+    let in_volta_home = volta_home.is_some_and(|home| path.starts_with(home))
+        || path
+            .components()
+            .any(|component| component.as_os_str().eq_ignore_ascii_case(".volta"));
+
+    is_shim_binary || in_volta_home
+}
+
+/// Homebrew keeps every formula in its Cellar, which is linked into PATH
+fn is_homebrew(canonical: &Path) -> bool {
+    match std::env::var_os("HOMEBREW_CELLAR") {
+        Some(cellar) => canonical.starts_with(cellar),
+        None => canonical
+            .components()
+            .any(|component| component.as_os_str() == "Cellar"),
+    }
+}
+
+/// What an asdf shim runs, from `asdf which yarn`
+fn asdf_which_yarn() -> Option<PathBuf> {
+    let output = exec::output_with_timeout(
+        exec::command("asdf").args(["which", "yarn"]),
+        "asdf which yarn",
+    );
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+
+    (output.status.success() && !path.is_empty()).then(|| PathBuf::from(path))
+}
+
+/// The prefix of the global npm install `yarn` is a part of, like
+/// `/usr/local` for `/usr/local/lib/node_modules/yarn/bin/yarn.js`. Windows
+/// has no `lib` directory in between. Only if the package really is yarn
+fn npm_prefix(canonical: &Path) -> Option<PathBuf> {
+    let package = canonical
+        .ancestors()
+        .find(|dir| dir.ends_with("node_modules/yarn"))?;
+    let manifest = fs::read_to_string(package.join("package.json")).ok()?;
+    let name = serde_json::from_str::<serde_json::Value>(&manifest)
+        .ok()?
+        .get("name")?
+        .as_str()
+        .map(str::to_owned);
+
+    if name.as_deref() != Some("yarn") {
+        return None;
+    }
+
+    let node_modules = package.parent()?;
+    let parent = node_modules.parent()?;
+
+    if parent.file_name().is_some_and(|name| name == "lib") {
+        parent.parent().map(Path::to_path_buf)
+    } else {
+        Some(parent.to_path_buf())
+    }
+}
@@ -0,0 +1,116 @@
+//! Runs the whole pipeline against the mocks, without a network and without
+//! node or yarn. Needs `--features mock`
+#![cfg(all(feature = "mock", unix))]
+
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+const FIXTURE: &str = "redwood-main/__fixtures__/test-project-rsc-kitchen-sink";
+
+/// The Redwood repo's archive, with nothing in it but a small template
+fn write_archive(path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+    let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+    let files = [
+        (
+            "package.json",
+            r#"{
+  "name": "test-project-rsc-kitchen-sink",
+  "private": true,
+  "workspaces": ["web"],
+  "packageManager": "yarn@4.5.0",
+  "devDependencies": { "@redwoodjs/core": "8.0.0" }
+}
+"#,
+        ),
+        (
+            "web/package.json",
+            r#"{
+  "name": "web",
+  "dependencies": { "@redwoodjs/web": "8.0.0" }
+}
+"#,
+        ),
+    ];
+
+    for (name, contents) in files {
+        zip.start_file(
+            format!("{FIXTURE}/{name}"),
+            zip::write::FileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(contents.as_bytes()).unwrap();
+    }
+
+    zip.finish().unwrap();
+}
+
+/// Executables that are only there to be found in PATH. The mock runner
+/// runs them instead
+fn write_fake_bins(dir: &Path) {
+    fs::create_dir_all(dir).unwrap();
+
+    for name in ["node", "yarn"] {
+        let path = dir.join(name);
+        fs::write(&path, "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
+
+#[test]
+fn creates_a_project_through_the_mocks() {
+    let root = tempfile::tempdir().unwrap();
+    let mock_dir = root.path().join("mock");
+    let home = root.path().join("home");
+    let bin = root.path().join("bin");
+    let project = root.path().join("project");
+
+    write_archive(&mock_dir.join("http/github.com/redwoodjs/redwood/archive/refs/heads/main.zip"));
+    fs::create_dir_all(mock_dir.join("commands")).unwrap();
+    fs::write(mock_dir.join("commands/node"), "v20.11.0\n").unwrap();
+    fs::write(mock_dir.join("commands/yarn"), "4.5.0\n").unwrap();
+    write_fake_bins(&bin);
+    fs::create_dir_all(&home).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file_extract"))
+        .args([
+            "--via",
+            "zip",
+            "--canary-version",
+            "9.9.9",
+            "--yes",
+            "--skip-git",
+        ])
+        .arg(&project)
+        .env_clear()
+        .env("RW_QUICKSTART_MOCK_DIR", &mock_dir)
+        .env("PATH", &bin)
+        .env("HOME", &home)
+        .env("XDG_CACHE_HOME", &home)
+        .env("XDG_CONFIG_HOME", &home)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let requests = fs::read_to_string(mock_dir.join("requests.log")).unwrap();
+    assert!(
+        requests.contains("GET https://github.com/redwoodjs/redwood/archive/refs/heads/main.zip")
+    );
+
+    let commands = fs::read_to_string(mock_dir.join("commands.log")).unwrap();
+    assert!(commands.contains(&format!("yarn install (in {})", project.to_string_lossy())));
+
+    let package_json = fs::read_to_string(project.join("package.json")).unwrap();
+    assert!(package_json.contains(r#""@redwoodjs/core": "9.9.9""#));
+    let web_package_json = fs::read_to_string(project.join("web/package.json")).unwrap();
+    assert!(web_package_json.contains(r#""@redwoodjs/web": "9.9.9""#));
+}